@@ -1,21 +1,37 @@
 use bytes::Bytes;
-use log::{debug, error};
+use log::debug;
+use std::fmt;
 use std::io;
 use std::io::Error;
 use std::net::SocketAddr;
+use std::time::Duration;
 use stun_rs::attrs::address_attr::AddressAttr;
 use stun_rs::attrs::change_request::ChangeRequest;
+use stun_rs::attrs::errcode_attr::ErrcodeAttr;
 use stun_rs::attrs::response_port::ResponsePort;
+use stun_rs::attrs::software::Software;
 use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::builder::MessageBuilder;
 use stun_rs::constants::{
-    ATTR_MAPPED_ADDRESS, ATTR_OTHER_ADDRESS, ATTR_RESPONSE_ORIGIN, ATTR_XOR_MAPPED_ADDRESS,
-    MESSAGE_TYPE_BIND_REQ,
+    ATTR_ALTERNATE_SERVER, ATTR_CHANGED_ADDRESS, ATTR_ERROR_CODE, ATTR_MAPPED_ADDRESS,
+    ATTR_OTHER_ADDRESS, ATTR_XOR_MAPPED_ADDRESS, ATTR_XOR_MAPPED_ADDRESS_LEGACY,
+    ERROR_CODE_TRY_ALTERNATE, MESSAGE_TYPE_BIND_REQ,
 };
 use stun_rs::error::{ParsePacketErr, ValidateErr};
-use stun_rs::header::{Header, TransId};
+use stun_rs::header::TransId;
 use stun_rs::packet::Packet;
-use stun_rs::util::{new_trans_id, print_bytes};
+use stun_rs::util::{new_trans_id, print_packet};
 use tokio::net::UdpSocket;
+use tokio::time::{timeout_at, Instant};
+
+// 最多跟随几次 300 重定向
+pub const MAX_REDIRECTS: usize = 3;
+
+// rfc 3489, 9.3
+// 重传间隔从 100ms 开始翻倍, 最大 1.6s, 9.5s 之后还没有响应认为没有响应
+const RTO_INITIAL: Duration = Duration::from_millis(100);
+const RTO_MAX: Duration = Duration::from_millis(1600);
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(9500);
 
 #[derive(Debug)]
 pub struct ProbeError(pub String);
@@ -28,13 +44,13 @@ impl From<io::Error> for ProbeError {
 
 impl From<ParsePacketErr> for ProbeError {
     fn from(e: ParsePacketErr) -> Self {
-        ProbeError(format!("{:?}", e))
+        ProbeError(format!("{}", e))
     }
 }
 
 impl From<ValidateErr> for ProbeError {
     fn from(e: ValidateErr) -> Self {
-        ProbeError(e.0)
+        ProbeError(format!("{}", e))
     }
 }
 
@@ -44,12 +60,50 @@ impl From<String> for ProbeError {
     }
 }
 
+// rfc 3489, 5
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NatType {
+    OpenInternet,
+    FullCone,
+    RestrictedCone,
+    PortRestrictedCone,
+    SymmetricNat,
+    SymmetricUdpFirewall,
+    UdpBlocked,
+    // test I 发到 changed address 没有响应, 无法继续判断
+    Unknown,
+}
+
+impl fmt::Display for NatType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NatType::OpenInternet => "Open Internet",
+            NatType::FullCone => "Full Cone",
+            NatType::RestrictedCone => "Restricted Cone",
+            NatType::PortRestrictedCone => "Port Restricted Cone",
+            NatType::SymmetricNat => "Symmetric NAT",
+            NatType::SymmetricUdpFirewall => "Symmetric UDP Firewall",
+            NatType::UdpBlocked => "UDP Blocked",
+            NatType::Unknown => "Unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 //--------------------------------------
-pub struct ResponseAddressAttr {
+// probe_nat 的结果, software 和 mapped address 来自 test I 的响应
+#[derive(Debug, Clone)]
+pub struct ProbeResult {
+    pub nat_type: NatType,
+    pub software: Option<String>,
+    pub mapped_address: Option<SocketAddr>,
+}
+
+// nat 检测每次请求需要的地址
+pub struct BindingResult {
     pub mapped_address: SocketAddr,
-    pub response_origin: SocketAddr,
-    pub other_address: SocketAddr,
-    pub xor_mapped_address: SocketAddr,
+    // rfc 5780 的 other-address, 或者 rfc 3489 的 changed-address
+    pub changed_address: Option<SocketAddr>,
 }
 
 //---------------------------------------
@@ -57,117 +111,424 @@ fn new_request(
     trans_id: TransId,
     change_request: Option<(bool, bool)>,
     response_port: Option<u16>,
-) -> Packet {
-    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, trans_id);
-    let mut request = Packet::new(header, vec![]);
+) -> Result<Bytes, ParsePacketErr> {
+    MessageBuilder::new(MESSAGE_TYPE_BIND_REQ)
+        .trans_id(trans_id)
+        .attr_opt(change_request.map(|(ip, port)| ChangeRequest::new(ip, port)))
+        .attr_opt(response_port.map(ResponsePort::new))
+        .build()
+}
 
-    if let Some((change_ip, change_port)) = change_request {
-        let attr = ChangeRequest::new(change_ip, change_port);
-        request.add_attr(attr.into());
+// nat 检测的下一步
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeStep {
+    // test II (change ip + port)
+    Test2,
+    // test I, 发到 changed address
+    Test1Changed,
+    // test III (change port)
+    Test3,
+    Done(NatType),
+}
+
+// 已经完成的测试结果, 还没有做的测试是 None
+// test I 总是第一个做, test1 是它的 mapped address, 没有响应时是 None
+#[derive(Debug, Clone, Default)]
+pub struct ProbeResults {
+    pub test1: Option<SocketAddr>,
+    // test II / test III 是否有响应
+    pub test2: Option<bool>,
+    // Some(None) 表示发到 changed address 没有响应
+    pub test1_changed: Option<Option<SocketAddr>>,
+    pub test3: Option<bool>,
+}
+
+// rfc 3489, 10.1
+//
+// test I: 没有响应 -> UDP Blocked
+// mapped address 和本地地址相同 (没有 nat):
+//     test II (change ip + port): 有响应 -> Open Internet, 没有响应 -> Symmetric UDP Firewall
+// 否则:
+//     test II: 有响应 -> Full Cone
+//     test I 发到 changed address: mapped address 不同 -> Symmetric NAT, 没有响应 -> Unknown
+//     test III (change port): 有响应 -> Restricted Cone, 没有响应 -> Port Restricted Cone
+pub fn next_step(local_addr: SocketAddr, results: &ProbeResults) -> ProbeStep {
+    let mapped_address = match results.test1 {
+        None => {
+            return ProbeStep::Done(NatType::UdpBlocked);
+        }
+        Some(v) => v,
+    };
+
+    let test2 = match results.test2 {
+        None => {
+            return ProbeStep::Test2;
+        }
+        Some(v) => v,
+    };
+
+    if mapped_address == local_addr {
+        return match test2 {
+            true => ProbeStep::Done(NatType::OpenInternet),
+            false => ProbeStep::Done(NatType::SymmetricUdpFirewall),
+        };
     }
 
-    if let Some(port) = response_port {
-        let attr = ResponsePort::new(port);
-        request.add_attr(attr.into());
+    if test2 {
+        return ProbeStep::Done(NatType::FullCone);
     }
 
-    request
+    match results.test1_changed {
+        None => {
+            return ProbeStep::Test1Changed;
+        }
+        Some(None) => {
+            return ProbeStep::Done(NatType::Unknown);
+        }
+        Some(Some(v)) if v != mapped_address => {
+            return ProbeStep::Done(NatType::SymmetricNat);
+        }
+        Some(Some(_)) => {}
+    }
+
+    match results.test3 {
+        None => ProbeStep::Test3,
+        Some(true) => ProbeStep::Done(NatType::RestrictedCone),
+        Some(false) => ProbeStep::Done(NatType::PortRestrictedCone),
+    }
+}
+
+// 按 next_step 依次做测试
+pub async fn probe_nat(sock: &UdpSocket, server: SocketAddr) -> Result<ProbeResult, ProbeError> {
+    let local_addr = resolve_local_addr(sock, server).await?;
+    debug!("local addr: {}", local_addr);
+    let mut results = ProbeResults::default();
+    let mut software = None;
+
+    // test I, 收到 300 时之后的测试都发到 alternate-server
+    let (server, changed_address) = match request_with_redirect(sock, server).await? {
+        None => (server, None),
+        Some((server, response)) => {
+            let test1 = find_binding_result(&response)?;
+            debug!(
+                "test I: mapped address: {}, changed address: {:?}",
+                test1.mapped_address, test1.changed_address
+            );
+            software = response.get::<Software>()?.map(|v| v.software);
+
+            results.test1 = Some(test1.mapped_address);
+            (server, test1.changed_address)
+        }
+    };
+
+    loop {
+        match next_step(local_addr, &results) {
+            ProbeStep::Done(v) => {
+                return Ok(ProbeResult {
+                    nat_type: v,
+                    software,
+                    mapped_address: results.test1,
+                });
+            }
+            ProbeStep::Test2 => {
+                let test2 = binding_test(sock, server, Some((true, true))).await?;
+                debug!("test II: {}", test2.is_some());
+                results.test2 = Some(test2.is_some());
+            }
+            ProbeStep::Test1Changed => {
+                // 只有这一步需要 changed address
+                let changed_address = match changed_address {
+                    Some(v) => v,
+                    None => {
+                        return Err(ProbeError(format!(
+                            "can't find attr: {} or {}",
+                            ATTR_OTHER_ADDRESS, ATTR_CHANGED_ADDRESS
+                        )));
+                    }
+                };
+
+                let test1_changed = binding_test(sock, changed_address, None)
+                    .await?
+                    .map(|v| v.mapped_address);
+                debug!(
+                    "test I ({}): mapped address: {:?}",
+                    changed_address, test1_changed
+                );
+                results.test1_changed = Some(test1_changed);
+            }
+            ProbeStep::Test3 => {
+                let test3 = binding_test(sock, server, Some((false, true))).await?;
+                debug!("test III: {}", test3.is_some());
+                results.test3 = Some(test3.is_some());
+            }
+        }
+    }
+}
+
+// socket 绑定在 0.0.0.0 时 local_addr 不是 mapped address 可能的值,
+// 用一个 connect 到 server 的临时 socket 取系统选择的本地 ip, 端口不变
+async fn resolve_local_addr(
+    sock: &UdpSocket,
+    server: SocketAddr,
+) -> Result<SocketAddr, ProbeError> {
+    let local_addr = sock.local_addr()?;
+    if !local_addr.ip().is_unspecified() {
+        return Ok(local_addr);
+    }
+
+    let bind_addr = SocketAddr::new(local_addr.ip(), 0);
+    let tmp = UdpSocket::bind(bind_addr).await?;
+    tmp.connect(server).await?;
+    Ok(SocketAddr::new(tmp.local_addr()?.ip(), local_addr.port()))
+}
+
+// 收到 300 时换到 alternate-server 重新请求, 最多跟随几次
+// 返回最后请求的 server 和它的响应, 第一个 server 没有响应时返回 None
+async fn request_with_redirect(
+    sock: &UdpSocket,
+    server: SocketAddr,
+) -> Result<Option<(SocketAddr, Packet)>, ProbeError> {
+    let mut server = server;
+    let mut visited = vec![server];
+
+    loop {
+        let response = match binding_request(sock, server, None).await? {
+            Some(v) => v,
+            None if visited.len() == 1 => {
+                return Ok(None);
+            }
+            None => {
+                return Err(ProbeError(format!("no response from {}", server)));
+            }
+        };
+        let alternate = match next_redirect(&visited, &response)? {
+            None => {
+                return Ok(Some((server, response)));
+            }
+            Some(v) => v,
+        };
+
+        debug!("redirect: {} -> {}", server, alternate);
+        visited.push(alternate);
+        server = alternate;
+    }
 }
 
-pub async fn probe_nat(sock: &UdpSocket, server: SocketAddr) {
-    match probe_nat_1(sock, server).await {
-        Ok(v) => {
-            debug!("mapped_address:    {}", v.mapped_address);
-            debug!("response_origin:   {}", v.response_origin);
-            debug!("other_address:     {}", v.other_address);
-            debug!("xor_mapped_address:{}", v.xor_mapped_address);
+// 收到 300 时返回下一个要请求的 alternate-server, 其他成功响应返回 None
+// visited 是已经请求过的 server, 第一个是最初的 server
+pub fn next_redirect(
+    visited: &[SocketAddr],
+    response: &Packet,
+) -> Result<Option<SocketAddr>, ProbeError> {
+    let alternate = match find_alternate_server(response)? {
+        None => {
+            return Ok(None);
         }
-        Err(e) => {
-            error!("error, probe_nat_1, {:?}", e);
+        Some(v) => v,
+    };
+
+    // rfc 8489, 10, 不能重定向回已经请求过的 server
+    if visited.contains(&alternate) {
+        return Err(ProbeError(format!("redirect loop: {}", alternate)));
+    }
+    if visited.len() > MAX_REDIRECTS {
+        return Err(ProbeError(format!(
+            "too many redirects, last: {}",
+            alternate
+        )));
+    }
+
+    Ok(Some(alternate))
+}
+
+// nat 检测中的一次请求, 没有响应时返回 None
+async fn binding_test(
+    sock: &UdpSocket,
+    server: SocketAddr,
+    change_request: Option<(bool, bool)>,
+) -> Result<Option<BindingResult>, ProbeError> {
+    let response = match binding_request(sock, server, change_request).await? {
+        Some(v) => v,
+        None => {
+            return Ok(None);
         }
+    };
+
+    if let Some(v) = find_alternate_server(&response)? {
+        return Err(ProbeError(format!("unexpected redirect: {}", v)));
     }
+
+    find_binding_result(&response).map(Some)
 }
 
-pub async fn probe_nat_1(
+// 超时重传, 一直没有响应时返回 None
+// 只接受 transaction id 一致的响应, 之前请求的重传的响应直接丢弃
+async fn binding_request(
     sock: &UdpSocket,
     server: SocketAddr,
-) -> Result<ResponseAddressAttr, ProbeError> {
+    change_request: Option<(bool, bool)>,
+) -> Result<Option<Packet>, ProbeError> {
     let trans_id = new_trans_id();
-    let mut recv_buf = vec![0u8; 32 * 1024];
 
-    let req = new_request(trans_id, None, None);
-    let buf = req.pack();
+    let buf = new_request(trans_id, change_request, None)?;
     debug!("request len: {}", buf.len());
     debug!(
         "{:?} --> {}\n{}",
         sock.local_addr().unwrap(),
         server,
-        print_bytes(&buf, " ", 8)
+        print_packet(&buf)
     );
 
-    let sent = sock.send_to(&buf, server).await?;
-    debug!("sent: {}", sent);
+    let end = Instant::now() + REQUEST_TIMEOUT;
+    let mut rto = RTO_INITIAL;
+    loop {
+        let sent = sock.send_to(&buf, server).await?;
+        debug!("sent: {}", sent);
 
-    let (len, remote_addr) = sock.recv_from(&mut recv_buf).await?;
-    let buf = Bytes::copy_from_slice(&recv_buf[..len]);
-    debug!("recv len: {}", buf.len());
-    debug!(
-        "{:?} <-- {}\n{}",
-        sock.local_addr().unwrap(),
-        remote_addr,
-        print_bytes(&buf, " ", 8)
-    );
+        let deadline = end.min(Instant::now() + rto);
+        if let Some(v) = recv_response(sock, &trans_id, deadline).await? {
+            return Ok(Some(v));
+        }
 
-    let response = Packet::unpack(buf)?;
-    match response.validate() {
-        None => {}
-        Some(e) => {
-            return Err(e.into());
+        if Instant::now() >= end {
+            debug!("no response from {}", server);
+            return Ok(None);
+        }
+        rto = RTO_MAX.min(rto * 2);
+    }
+}
+
+// deadline 之前没有收到响应时返回 None
+async fn recv_response(
+    sock: &UdpSocket,
+    trans_id: &TransId,
+    deadline: Instant,
+) -> Result<Option<Packet>, ProbeError> {
+    let mut recv_buf = vec![0u8; 32 * 1024];
+
+    loop {
+        let (len, remote_addr) = match timeout_at(deadline, sock.recv_from(&mut recv_buf)).await {
+            Ok(v) => v?,
+            Err(_) => {
+                return Ok(None);
+            }
+        };
+        let buf = Bytes::copy_from_slice(&recv_buf[..len]);
+        debug!("recv len: {}", buf.len());
+        debug!(
+            "{:?} <-- {}\n{}",
+            sock.local_addr().unwrap(),
+            remote_addr,
+            print_packet(&buf)
+        );
+
+        let response = match Packet::unpack(buf) {
+            Ok(v) => v,
+            Err(e) => {
+                debug!("drop, {}", e);
+                continue;
+            }
+        };
+        if response.header.trans_id != *trans_id {
+            debug!("drop, transaction id not match");
+            continue;
+        }
+
+        match response.validate() {
+            None => {}
+            Some(e) => {
+                return Err(e.into());
+            }
+        };
+
+        // rfc 5389, 7.3.3
+        let unknown = response.unknown_attrs();
+        if !unknown.is_empty() {
+            return Err(ProbeError(format!("unknown attrs: {:x?}", unknown)));
+        }
+
+        return Ok(Some(response));
+    }
+}
+
+// 300 返回 alternate-server, 其他错误响应直接返回错误
+pub fn find_alternate_server(packet: &Packet) -> Result<Option<SocketAddr>, ProbeError> {
+    if !packet.header.msg_type.is_error_response() {
+        return Ok(None);
+    }
+
+    let errcode = match packet.get::<ErrcodeAttr>()? {
+        Some(v) => v,
+        None => {
+            return Err(ProbeError(format!("can't find attr: {}", ATTR_ERROR_CODE)));
         }
     };
+    if errcode.code != ERROR_CODE_TRY_ALTERNATE {
+        return Err(ProbeError(format!(
+            "error response: {} {}",
+            errcode.code, errcode.msg
+        )));
+    }
 
-    find_response_attrs(&response)
+    match packet.get_by_type::<AddressAttr>(ATTR_ALTERNATE_SERVER)? {
+        Some(v) => Ok(Some(v.address)),
+        None => Err(ProbeError(format!(
+            "can't find attr: {}",
+            ATTR_ALTERNATE_SERVER
+        ))),
+    }
 }
 
-fn find_response_attrs(packet: &Packet) -> Result<ResponseAddressAttr, ProbeError> {
-    let mapped_address = find_address_attr(packet, ATTR_MAPPED_ADDRESS)?;
-    let response_origin = find_address_attr(packet, ATTR_RESPONSE_ORIGIN)?;
-    let other_address = find_address_attr(packet, ATTR_OTHER_ADDRESS)?;
-    let xor_mapped_address = find_xor_address_attr(packet)?;
+// rfc 3489 的服务端没有 xor-mapped-address, 使用 mapped-address
+fn find_binding_result(packet: &Packet) -> Result<BindingResult, ProbeError> {
+    let mapped_address = match get_xor_address_attr(packet)? {
+        Some(v) => v,
+        None => find_address_attr(packet, ATTR_MAPPED_ADDRESS)?,
+    };
+
+    let changed_address = match packet.get_by_type::<AddressAttr>(ATTR_OTHER_ADDRESS)? {
+        Some(v) => Some(v.address),
+        None => packet
+            .get_by_type::<AddressAttr>(ATTR_CHANGED_ADDRESS)?
+            .map(|v| v.address),
+    };
 
-    Ok(ResponseAddressAttr {
+    Ok(BindingResult {
         mapped_address,
-        response_origin,
-        other_address,
-        xor_mapped_address,
+        changed_address,
     })
 }
 
 fn find_address_attr(packet: &Packet, attr_type: u16) -> Result<SocketAddr, ProbeError> {
-    for attr in packet.attrs.iter() {
-        if attr.attr_type == attr_type {
-            let address_attr: AddressAttr = attr.clone().try_into()?;
-            return Ok(address_attr.address);
-        }
+    match packet.get_by_type::<AddressAttr>(attr_type)? {
+        Some(v) => Ok(v.address),
+        None => Err(ProbeError(format!("can't find attr: {}", attr_type))),
     }
-
-    Err(ProbeError(format!("can't find attr: {}", attr_type)))
 }
 
-fn find_xor_address_attr(packet: &Packet) -> Result<SocketAddr, ProbeError> {
-    let trans_id = &packet.header.trans_id;
+// rfc 3489 的响应没有 magic cookie, 不使用 xor-mapped-address
+fn get_xor_address_attr(packet: &Packet) -> Result<Option<SocketAddr>, ProbeError> {
+    if packet.is_classic() {
+        return Ok(None);
+    }
 
-    for attr in packet.attrs.iter() {
-        if attr.attr_type == ATTR_XOR_MAPPED_ADDRESS {
-            let address_attr: XorMappedAddress =
-                XorMappedAddress::from_base_attr(attr.clone(), trans_id)?;
+    if let Some(v) = packet.get::<XorMappedAddress>()? {
+        return Ok(Some(v.address));
+    }
 
-            return Ok(address_attr.address);
+    // 老的服务器使用 0x8020, 编码和 xor-mapped-address 一样
+    match packet
+        .attrs
+        .iter()
+        .find(|x| x.attr_type == ATTR_XOR_MAPPED_ADDRESS_LEGACY)
+    {
+        Some(v) => {
+            let mut raw = v.clone();
+            raw.attr_type = ATTR_XOR_MAPPED_ADDRESS;
+            Ok(Some(
+                XorMappedAddress::from_base_attr(raw, &packet.header)?.address,
+            ))
         }
+        None => Ok(None),
     }
-
-    Err(ProbeError(format!(
-        "can't find attr: {}",
-        ATTR_XOR_MAPPED_ADDRESS
-    )))
 }
@@ -2,8 +2,8 @@ use std::net::{IpAddr, SocketAddr};
 
 use clap::builder::ValueParser;
 use clap::{Arg, Command};
-use log::debug;
 use client::client::probe_nat;
+use log::{debug, error};
 use tokio::net::UdpSocket;
 
 const APP_NAME: &str = env!("CARGO_PKG_NAME");
@@ -90,5 +90,19 @@ async fn main() {
     let local_addr = sock.local_addr();
     debug!("local addr: {:?}", local_addr);
 
-    probe_nat(&sock, server).await;
+    match probe_nat(&sock, server).await {
+        Ok(v) => {
+            println!("nat type: {}", v.nat_type);
+            if let Some(mapped_address) = v.mapped_address {
+                println!("mapped address: {}", mapped_address);
+            }
+            if let Some(software) = v.software {
+                println!("server software: {}", software);
+            }
+        }
+        Err(e) => {
+            error!("error, probe_nat, {:?}", e);
+            std::process::exit(1);
+        }
+    }
 }
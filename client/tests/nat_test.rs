@@ -0,0 +1,82 @@
+use client::client::{next_step, NatType, ProbeResults, ProbeStep};
+use std::net::SocketAddr;
+
+const LOCAL: &str = "192.168.1.2:5000";
+const MAPPED: &str = "203.0.113.1:6000";
+
+fn addr(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+fn step(results: &ProbeResults) -> ProbeStep {
+    next_step(addr(LOCAL), results)
+}
+
+#[test]
+pub fn test_udp_blocked() {
+    let results = ProbeResults::default();
+    assert_eq!(step(&results), ProbeStep::Done(NatType::UdpBlocked));
+}
+
+// 没有 nat, 只需要 test I 和 test II
+#[test]
+pub fn test_no_nat() {
+    let mut results = ProbeResults {
+        test1: Some(addr(LOCAL)),
+        ..Default::default()
+    };
+    assert_eq!(step(&results), ProbeStep::Test2);
+
+    results.test2 = Some(true);
+    assert_eq!(step(&results), ProbeStep::Done(NatType::OpenInternet));
+
+    results.test2 = Some(false);
+    assert_eq!(
+        step(&results),
+        ProbeStep::Done(NatType::SymmetricUdpFirewall)
+    );
+}
+
+#[test]
+pub fn test_full_cone() {
+    let results = ProbeResults {
+        test1: Some(addr(MAPPED)),
+        test2: Some(true),
+        ..Default::default()
+    };
+    assert_eq!(step(&results), ProbeStep::Done(NatType::FullCone));
+}
+
+#[test]
+pub fn test_symmetric_nat() {
+    let mut results = ProbeResults {
+        test1: Some(addr(MAPPED)),
+        test2: Some(false),
+        ..Default::default()
+    };
+    assert_eq!(step(&results), ProbeStep::Test1Changed);
+
+    results.test1_changed = Some(Some(addr("203.0.113.1:6001")));
+    assert_eq!(step(&results), ProbeStep::Done(NatType::SymmetricNat));
+
+    // changed address 没有响应
+    results.test1_changed = Some(None);
+    assert_eq!(step(&results), ProbeStep::Done(NatType::Unknown));
+}
+
+#[test]
+pub fn test_restricted_cone() {
+    let mut results = ProbeResults {
+        test1: Some(addr(MAPPED)),
+        test2: Some(false),
+        test1_changed: Some(Some(addr(MAPPED))),
+        ..Default::default()
+    };
+    assert_eq!(step(&results), ProbeStep::Test3);
+
+    results.test3 = Some(true);
+    assert_eq!(step(&results), ProbeStep::Done(NatType::RestrictedCone));
+
+    results.test3 = Some(false);
+    assert_eq!(step(&results), ProbeStep::Done(NatType::PortRestrictedCone));
+}
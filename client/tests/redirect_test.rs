@@ -0,0 +1,83 @@
+use client::client::{find_alternate_server, next_redirect, MAX_REDIRECTS};
+use std::net::SocketAddr;
+use stun_rs::attrs::address_attr::AddressAttr;
+use stun_rs::attrs::errcode_attr::ErrcodeAttr;
+use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::constants::*;
+use stun_rs::header::Header;
+use stun_rs::packet::Packet;
+use stun_rs::util;
+
+const SERVER: &str = "192.0.2.1:3478";
+const ALTERNATE: &str = "192.0.2.2:3478";
+
+fn addr(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+fn new_redirect(alternate: SocketAddr) -> Packet {
+    let header = Header::new(MESSAGE_TYPE_BIND_ERR_RES, 0, util::new_trans_id());
+    Packet::new(
+        header,
+        vec![
+            ErrcodeAttr::new(ERROR_CODE_TRY_ALTERNATE, "try alternate").into(),
+            AddressAttr::new(ATTR_ALTERNATE_SERVER, alternate).into(),
+        ],
+    )
+}
+
+#[test]
+pub fn test_find_alternate_server() {
+    let packet = new_redirect(addr(ALTERNATE));
+    assert_eq!(
+        find_alternate_server(&packet).unwrap(),
+        Some(addr(ALTERNATE))
+    );
+
+    // 成功响应不需要重定向
+    let trans_id = util::new_trans_id();
+    let header = Header::new(MESSAGE_TYPE_BIND_RES, 0, trans_id);
+    let packet = Packet::new(
+        header,
+        vec![XorMappedAddress::new(trans_id, addr("203.0.113.1:6000")).into()],
+    );
+    assert_eq!(find_alternate_server(&packet).unwrap(), None);
+
+    // 其他错误响应, 或者 300 没有 alternate-server
+    let header = Header::new(MESSAGE_TYPE_BIND_ERR_RES, 0, util::new_trans_id());
+    let packet = Packet::new(
+        header.clone(),
+        vec![ErrcodeAttr::new(ERROR_CODE_BAD_REQUEST, "bad request").into()],
+    );
+    assert!(find_alternate_server(&packet).is_err());
+
+    let packet = Packet::new(
+        header,
+        vec![ErrcodeAttr::new(ERROR_CODE_TRY_ALTERNATE, "try alternate").into()],
+    );
+    assert!(find_alternate_server(&packet).is_err());
+}
+
+#[test]
+pub fn test_redirect_loop() {
+    let visited = [addr(SERVER), addr(ALTERNATE)];
+    let result = next_redirect(&visited, &new_redirect(addr(SERVER)));
+    assert!(result.unwrap_err().0.contains("redirect loop"));
+
+    let result = next_redirect(&visited[..1], &new_redirect(addr(ALTERNATE)));
+    assert_eq!(result.unwrap(), Some(addr(ALTERNATE)));
+}
+
+#[test]
+pub fn test_max_redirects() {
+    let mut visited = vec![addr(SERVER)];
+    for i in 0..MAX_REDIRECTS {
+        let alternate = addr(&format!("192.0.2.{}:3478", 10 + i));
+        let next = next_redirect(&visited, &new_redirect(alternate)).unwrap();
+        assert_eq!(next, Some(alternate));
+        visited.push(alternate);
+    }
+
+    let result = next_redirect(&visited, &new_redirect(addr(ALTERNATE)));
+    assert!(result.unwrap_err().0.contains("too many redirects"));
+}
@@ -0,0 +1,54 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use stun_rs::attrs::attribute::Attribute;
+use stun_rs::attrs::RawAttr;
+use stun_rs::constants::MAX_MESSAGE_SIZE;
+use stun_rs::packet::{DecodeOptions, PackOptions, Packet};
+
+fuzz_target!(|data: &[u8]| {
+    // 按 padding 解析成功的包, 重新编码和收到的字节一致 (包括 padding 的内容)
+    if let Ok(packet) = Packet::unpack(Bytes::copy_from_slice(data)) {
+        assert_eq!(packet.pack().unwrap().as_ref(), data);
+    }
+
+    let packet = match Packet::unpack_with(Bytes::copy_from_slice(data), &DecodeOptions::lenient())
+    {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+
+    // attribute 解析之后再编码, 再解析一次结果不变
+    let mut attrs = Vec::new();
+    for raw in packet.attrs.iter() {
+        let attr = match Attribute::decode(raw, &packet.header) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+
+        let first: RawAttr = attr.into();
+        let again = Attribute::decode(&first, &packet.header).expect("decode encoded attr");
+        let second: RawAttr = again.into();
+        assert_eq!(first.attr_type, second.attr_type);
+        assert_eq!(first.value, second.value);
+        attrs.push(first);
+    }
+
+    // 重新组包, 加上 fingerprint 之后能按 padding 解析
+    let rebuilt = Packet::new(packet.header.clone(), attrs);
+    let options = PackOptions {
+        fingerprint: true,
+        ..Default::default()
+    };
+    // msg_len 超过 u16 时返回 TooLarge
+    let buf = match rebuilt.pack_with(&options) {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    if buf.len() <= MAX_MESSAGE_SIZE {
+        let again = Packet::unpack(buf.clone()).expect("unpack rebuilt packet");
+        assert!(again.verify_fingerprint().is_none());
+        assert_eq!(again.pack().unwrap(), buf);
+    }
+});
@@ -0,0 +1,24 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use stun_rs::attrs::RawAttr;
+use stun_rs::classify;
+use stun_rs::header::Header;
+use stun_rs::packet::{DecodeOptions, Packet};
+
+// 任意数据都不能 panic, 只能返回错误
+fuzz_target!(|data: &[u8]| {
+    let _ = classify::classify(data);
+    let _ = classify::classify_with_fingerprint(data);
+    let _ = Header::unpack_from(data);
+    let _ = RawAttr::unpack(Bytes::copy_from_slice(data));
+
+    for options in [
+        DecodeOptions::default(),
+        DecodeOptions::strict(),
+        DecodeOptions::lenient(),
+    ] {
+        let _ = Packet::unpack_with(Bytes::copy_from_slice(data), &options);
+    }
+});
@@ -0,0 +1,38 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use stun_rs::attrs::nonce::Nonce;
+use stun_rs::auth;
+use stun_rs::error::AttrValidator;
+use stun_rs::packet::DecodeOptions;
+use stun_rs::packet_ref::PacketRef;
+use stun_rs::util;
+
+const KEY: &[u8] = b"0123456789abcdef";
+
+// 解析成功之后服务端会做的事情: 检查, 解析每个 attribute, 验证 message-integrity, 打印日志
+fuzz_target!(|data: &[u8]| {
+    let _ = util::print_packet(data);
+
+    let packet = match PacketRef::parse_with(data, &DecodeOptions::lenient()) {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    let _ = packet.validate();
+    let _ = packet.has_unknown_attrs();
+    let _ = packet.to_string();
+    for attr in packet.attributes().flatten() {
+        let _ = attr.validate();
+        let _ = attr.to_string();
+    }
+
+    let packet = packet.to_packet();
+    let _ = packet.validate();
+    let _ = packet.unknown_attrs();
+    let _ = packet.verify_message_integrity(KEY);
+    let _ = packet.verify_message_integrity_sha256(KEY);
+    let _ = packet.verify_fingerprint();
+    if let Ok(Some(nonce)) = packet.get::<Nonce>() {
+        let _ = auth::parse_nonce_cookie(&nonce.nonce);
+    }
+});
@@ -1,20 +1,23 @@
-use crate::attrs::RawAttr;
+use crate::attrs::{RawAttr, RawAttrRef};
 use crate::constants::*;
 use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
-use bytes::{BufMut, BytesMut};
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
-use std::ops::Deref;
+use bytes::BufMut;
+use core::fmt;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 
 // 地址类的attribute
 //
-// mapped-address  response-origin   other-address
+// mapped-address  response-origin   other-address   alternate-server
 
 // ipv4: family: 0x01, 4 bytes
 // ipv6: family: 0x02, 16 bytes
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AddressAttr {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex_u16"))]
     pub attr_type: u16,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::display"))]
     pub address: SocketAddr,
 }
 
@@ -29,31 +32,47 @@ impl AddressAttr {
             || attr_type == ATTR_CHANGED_ADDRESS
             || attr_type == ATTR_OTHER_ADDRESS
             || attr_type == ATTR_RESPONSE_ORIGIN
+            || attr_type == ATTR_ALTERNATE_SERVER
     }
 }
 
-impl From<AddressAttr> for RawAttr {
-    fn from(attr: AddressAttr) -> Self {
-        let (family, port, ip_bytes, ip_len) = match &attr.address {
+impl AddressAttr {
+    // value 的长度, 8 或者 20, 不需要 padding
+    pub fn value_len(&self) -> usize {
+        match self.address {
+            SocketAddr::V4(_) => 4 + 4,
+            SocketAddr::V6(_) => 4 + 16,
+        }
+    }
+
+    // 包括 attr_type 和 attr_len, 不经过 RawAttr, 不分配内存
+    pub fn encode<B: BufMut>(&self, buf: &mut B) -> usize {
+        buf.put_u16(self.attr_type);
+        buf.put_u16(self.value_len() as u16);
+        self.encode_value(buf);
+        4 + self.value_len()
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_u8(0);
+        match &self.address {
             SocketAddr::V4(addr) => {
-                let ip_bytes: Vec<u8> = addr.ip().octets().into();
-                (ATTR_FAMILY_IPV4, addr.port(), ip_bytes, 4)
+                buf.put_u8(ATTR_FAMILY_IPV4);
+                buf.put_u16(addr.port());
+                buf.put_slice(&addr.ip().octets());
             }
             SocketAddr::V6(addr) => {
-                let ip_bytes: Vec<u8> = addr.ip().octets().into();
-                (ATTR_FAMILY_IPV6, addr.port(), ip_bytes, 16)
+                buf.put_u8(ATTR_FAMILY_IPV6);
+                buf.put_u16(addr.port());
+                buf.put_slice(&addr.ip().octets());
             }
-        };
-
-        let mut bytes_buf = BytesMut::with_capacity(4 + ip_len);
-
-        bytes_buf.put_u8(0);
-        bytes_buf.put_u8(family);
-        bytes_buf.put_u16(port);
-        bytes_buf.put_slice(&ip_bytes);
-        let value = bytes_buf.freeze();
+        }
+    }
+}
 
-        RawAttr::new(attr.attr_type, value)
+impl From<AddressAttr> for RawAttr {
+    fn from(attr: AddressAttr) -> Self {
+        RawAttr::from_typed(attr.attr_type, &attr)
     }
 }
 
@@ -61,17 +80,26 @@ impl TryFrom<RawAttr> for AddressAttr {
     type Error = ParsePacketErr;
 
     fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        RawAttrRef::from(&base_attr).try_into()
+    }
+}
+
+impl TryFrom<RawAttrRef<'_>> for AddressAttr {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttrRef<'_>) -> Result<Self, Self::Error> {
         let attr_type = base_attr.attr_type;
 
         // 从 value中解析
         let mut index = 0_usize;
-        let value = base_attr.value.deref();
+        let value = base_attr.value;
 
         if value.len() < 4 {
-            return Err(ParsePacketErr::BufSize(format!(
-                "attr buf len:{}",
-                value.len()
-            )));
+            return Err(ParsePacketErr::AttrLen {
+                attr_type,
+                expected: 4,
+                actual: value.len(),
+            });
         }
 
         index += 1;
@@ -86,7 +114,11 @@ impl TryFrom<RawAttr> for AddressAttr {
             ATTR_FAMILY_IPV4 => {
                 // 4 bytes
                 if index + 4 > value.len() {
-                    return Err(ParsePacketErr::BufSize("ipv4 buf len < 4".to_string()));
+                    return Err(ParsePacketErr::AttrLen {
+                        attr_type,
+                        expected: index + 4,
+                        actual: value.len(),
+                    });
                 }
                 let mut addr = [0_u8; 4];
                 addr.copy_from_slice(&value[index..index + 4]);
@@ -95,14 +127,21 @@ impl TryFrom<RawAttr> for AddressAttr {
             ATTR_FAMILY_IPV6 => {
                 // 16 bytes
                 if index + 16 > value.len() {
-                    return Err(ParsePacketErr::BufSize("ipv6 buf len < 16".to_string()));
+                    return Err(ParsePacketErr::AttrLen {
+                        attr_type,
+                        expected: index + 16,
+                        actual: value.len(),
+                    });
                 }
                 let mut addr = [0_u8; 16];
                 addr.copy_from_slice(&value[index..index + 16]);
                 SocketAddr::new(IpAddr::V6(Ipv6Addr::from(addr)), port)
             }
             v => {
-                return Err(ParsePacketErr::BadValue(format!("ip family: {}", v)));
+                return Err(ParsePacketErr::BadFamily {
+                    attr_type,
+                    family: v,
+                });
             }
         };
 
@@ -113,13 +152,8 @@ impl TryFrom<RawAttr> for AddressAttr {
 impl AttrValidator for AddressAttr {
     fn validate(&self) -> Option<ValidateErr> {
         // 检查 attr type
-        if !(self.attr_type == ATTR_MAPPED_ADDRESS
-            || self.attr_type == ATTR_SOURCE_ADDRESS
-            || self.attr_type == ATTR_CHANGED_ADDRESS
-            || self.attr_type == ATTR_OTHER_ADDRESS
-            || self.attr_type == ATTR_RESPONSE_ORIGIN)
-        {
-            return Some(ValidateErr(format!("wrong attr_type: {}", self.attr_type)));
+        if !AddressAttr::is_like_mapped_addr(self.attr_type) {
+            return Some(ValidateErr::WrongAttrType(self.attr_type));
         }
 
         // 检查 port
@@ -129,7 +163,15 @@ impl AttrValidator for AddressAttr {
             return None;
         }
 
-        let err_msg = format!("wrong port: {}", port);
-        Some(ValidateErr(err_msg))
+        Some(ValidateErr::BadPort {
+            attr_type: self.attr_type,
+            port,
+        })
+    }
+}
+
+impl fmt::Display for AddressAttr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.address)
     }
 }
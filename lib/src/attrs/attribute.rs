@@ -0,0 +1,574 @@
+use crate::attrs::address_attr::AddressAttr;
+use crate::attrs::change_request::ChangeRequest;
+use crate::attrs::errcode_attr::ErrcodeAttr;
+use crate::attrs::fingerprint::Fingerprint;
+use crate::attrs::message_integrity::MessageIntegrity;
+use crate::attrs::message_integrity_sha256::MessageIntegritySha256;
+use crate::attrs::nonce::Nonce;
+use crate::attrs::padding_attr::PaddingAttr;
+use crate::attrs::password_algorithm::{PasswordAlgorithm, PasswordAlgorithms};
+use crate::attrs::realm::Realm;
+use crate::attrs::registry::{AttrRegistry, CustomAttribute};
+use crate::attrs::response_port::ResponsePort;
+use crate::attrs::software::Software;
+use crate::attrs::unknown_attributes::UnknownAttributes;
+use crate::attrs::userhash::Userhash;
+use crate::attrs::username::Username;
+use crate::attrs::xor_address::XorMappedAddress;
+use crate::attrs::{AttrPadding, RawAttr, RawAttrRef};
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use crate::header::Header;
+use crate::util;
+use bytes::BufMut;
+use core::fmt;
+
+// 可以从 RawAttr 解析出来, 也可以直接编码的 attribute
+// xor-mapped-address 需要 header 中的 magic cookie 和 transaction id
+pub trait TypedAttr: Sized {
+    fn is_type(attr_type: u16) -> bool;
+
+    // value 的长度, 不包括 type, length 和 padding
+    fn value_len(&self) -> usize;
+
+    // 只写 value, 直接写到 buf, 不经过 RawAttr
+    fn encode_value<B: BufMut>(&self, buf: &mut B);
+
+    fn decode(raw: &RawAttr, header: &Header) -> Result<Self, ParsePacketErr>;
+
+    // 从借用的 buf 解析, 默认复制一份 value
+    fn decode_ref(raw: RawAttrRef<'_>, header: &Header) -> Result<Self, ParsePacketErr> {
+        Self::decode(&raw.to_owned(), header)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Attribute {
+    MappedAddress(AddressAttr),
+    ChangeRequest(ChangeRequest),
+    SourceAddress(AddressAttr),
+    ChangedAddress(AddressAttr),
+    Username(Username),
+    MessageIntegrity(MessageIntegrity),
+    ErrorCode(ErrcodeAttr),
+    UnknownAttributes(UnknownAttributes),
+    Realm(Realm),
+    Nonce(Nonce),
+    MessageIntegritySha256(MessageIntegritySha256),
+    PasswordAlgorithm(PasswordAlgorithm),
+    Userhash(Userhash),
+    Padding(PaddingAttr),
+    ResponsePort(ResponsePort),
+    PasswordAlgorithms(PasswordAlgorithms),
+    XorMappedAddress(XorMappedAddress),
+    Software(Software),
+    AlternateServer(AddressAttr),
+    Fingerprint(Fingerprint),
+    ResponseOrigin(AddressAttr),
+    OtherAddress(AddressAttr),
+
+    // AttrRegistry 中注册的 attribute
+    Custom(CustomAttribute),
+
+    // 不认识的 attribute, 原样保留
+    Unknown(RawAttr),
+}
+
+impl Attribute {
+    pub fn decode(raw: &RawAttr, header: &Header) -> Result<Self, ParsePacketErr> {
+        let attr = match raw.attr_type {
+            ATTR_MAPPED_ADDRESS => Attribute::MappedAddress(raw.clone().try_into()?),
+            ATTR_CHANGE_REQUEST => Attribute::ChangeRequest(raw.clone().try_into()?),
+            ATTR_SOURCE_ADDRESS => Attribute::SourceAddress(raw.clone().try_into()?),
+            ATTR_CHANGED_ADDRESS => Attribute::ChangedAddress(raw.clone().try_into()?),
+            ATTR_USERNAME => Attribute::Username(raw.clone().try_into()?),
+            ATTR_MESSAGE_INTEGRITY => Attribute::MessageIntegrity(raw.clone().try_into()?),
+            ATTR_ERROR_CODE => Attribute::ErrorCode(raw.clone().try_into()?),
+            ATTR_UNKNOWN_ATTRIBUTES => Attribute::UnknownAttributes(raw.clone().try_into()?),
+            ATTR_REALM => Attribute::Realm(raw.clone().try_into()?),
+            ATTR_NONCE => Attribute::Nonce(raw.clone().try_into()?),
+            ATTR_MESSAGE_INTEGRITY_SHA256 => {
+                Attribute::MessageIntegritySha256(raw.clone().try_into()?)
+            }
+            ATTR_PASSWORD_ALGORITHM => Attribute::PasswordAlgorithm(raw.clone().try_into()?),
+            ATTR_USERHASH => Attribute::Userhash(raw.clone().try_into()?),
+            ATTR_PADDING => Attribute::Padding(raw.clone().try_into()?),
+            ATTR_RESPONSE_PORT => Attribute::ResponsePort(raw.clone().try_into()?),
+            ATTR_PASSWORD_ALGORITHMS => Attribute::PasswordAlgorithms(raw.clone().try_into()?),
+            ATTR_XOR_MAPPED_ADDRESS => {
+                Attribute::XorMappedAddress(XorMappedAddress::decode(raw, header)?)
+            }
+            ATTR_SOFTWARE => Attribute::Software(raw.clone().try_into()?),
+            ATTR_ALTERNATE_SERVER => Attribute::AlternateServer(raw.clone().try_into()?),
+            ATTR_FINGERPRINT => Attribute::Fingerprint(raw.clone().try_into()?),
+            ATTR_RESPONSE_ORIGIN => Attribute::ResponseOrigin(raw.clone().try_into()?),
+            ATTR_OTHER_ADDRESS => Attribute::OtherAddress(raw.clone().try_into()?),
+            _ => Attribute::Unknown(raw.clone()),
+        };
+
+        Ok(attr)
+    }
+
+    // registry 中注册的 attribute 解析成 Attribute::Custom, 其它的使用内置的解析
+    pub fn decode_with(
+        raw: &RawAttr,
+        header: &Header,
+        registry: &AttrRegistry,
+    ) -> Result<Self, ParsePacketErr> {
+        match registry.decode(raw, header) {
+            Some(v) => v,
+            None => Attribute::decode(raw, header),
+        }
+    }
+
+    // 内置支持的 attribute
+    pub fn is_known(attr_type: u16) -> bool {
+        matches!(
+            attr_type,
+            ATTR_MAPPED_ADDRESS
+                | ATTR_CHANGE_REQUEST
+                | ATTR_SOURCE_ADDRESS
+                | ATTR_CHANGED_ADDRESS
+                | ATTR_USERNAME
+                | ATTR_MESSAGE_INTEGRITY
+                | ATTR_ERROR_CODE
+                | ATTR_UNKNOWN_ATTRIBUTES
+                | ATTR_REALM
+                | ATTR_NONCE
+                | ATTR_MESSAGE_INTEGRITY_SHA256
+                | ATTR_PASSWORD_ALGORITHM
+                | ATTR_USERHASH
+                | ATTR_PADDING
+                | ATTR_RESPONSE_PORT
+                | ATTR_PASSWORD_ALGORITHMS
+                | ATTR_XOR_MAPPED_ADDRESS
+                | ATTR_SOFTWARE
+                | ATTR_ALTERNATE_SERVER
+                | ATTR_FINGERPRINT
+                | ATTR_RESPONSE_ORIGIN
+                | ATTR_OTHER_ADDRESS
+        )
+    }
+
+    // 内置支持的 attribute 的名字, 不认识的返回 None
+    pub fn name(attr_type: u16) -> Option<&'static str> {
+        let name = match attr_type {
+            ATTR_MAPPED_ADDRESS => "MAPPED-ADDRESS",
+            ATTR_CHANGE_REQUEST => "CHANGE-REQUEST",
+            ATTR_SOURCE_ADDRESS => "SOURCE-ADDRESS",
+            ATTR_CHANGED_ADDRESS => "CHANGED-ADDRESS",
+            ATTR_USERNAME => "USERNAME",
+            ATTR_MESSAGE_INTEGRITY => "MESSAGE-INTEGRITY",
+            ATTR_ERROR_CODE => "ERROR-CODE",
+            ATTR_UNKNOWN_ATTRIBUTES => "UNKNOWN-ATTRIBUTES",
+            ATTR_REALM => "REALM",
+            ATTR_NONCE => "NONCE",
+            ATTR_MESSAGE_INTEGRITY_SHA256 => "MESSAGE-INTEGRITY-SHA256",
+            ATTR_PASSWORD_ALGORITHM => "PASSWORD-ALGORITHM",
+            ATTR_USERHASH => "USERHASH",
+            ATTR_PADDING => "PADDING",
+            ATTR_RESPONSE_PORT => "RESPONSE-PORT",
+            ATTR_PASSWORD_ALGORITHMS => "PASSWORD-ALGORITHMS",
+            ATTR_XOR_MAPPED_ADDRESS => "XOR-MAPPED-ADDRESS",
+            ATTR_SOFTWARE => "SOFTWARE",
+            ATTR_ALTERNATE_SERVER => "ALTERNATE-SERVER",
+            ATTR_FINGERPRINT => "FINGERPRINT",
+            ATTR_RESPONSE_ORIGIN => "RESPONSE-ORIGIN",
+            ATTR_OTHER_ADDRESS => "OTHER-ADDRESS",
+            _ => return None,
+        };
+
+        Some(name)
+    }
+
+    // 从借用的 buf 解析, 地址类等定长的 attribute 不分配内存
+    pub fn decode_ref(raw: RawAttrRef<'_>, header: &Header) -> Result<Self, ParsePacketErr> {
+        let attr = match raw.attr_type {
+            ATTR_MAPPED_ADDRESS => Attribute::MappedAddress(raw.try_into()?),
+            ATTR_CHANGE_REQUEST => Attribute::ChangeRequest(raw.try_into()?),
+            ATTR_SOURCE_ADDRESS => Attribute::SourceAddress(raw.try_into()?),
+            ATTR_CHANGED_ADDRESS => Attribute::ChangedAddress(raw.try_into()?),
+            ATTR_MESSAGE_INTEGRITY => Attribute::MessageIntegrity(raw.try_into()?),
+            ATTR_USERHASH => Attribute::Userhash(raw.try_into()?),
+            ATTR_RESPONSE_PORT => Attribute::ResponsePort(raw.try_into()?),
+            ATTR_XOR_MAPPED_ADDRESS => {
+                Attribute::XorMappedAddress(XorMappedAddress::decode_ref(raw, header)?)
+            }
+            ATTR_ALTERNATE_SERVER => Attribute::AlternateServer(raw.try_into()?),
+            ATTR_FINGERPRINT => Attribute::Fingerprint(raw.try_into()?),
+            ATTR_RESPONSE_ORIGIN => Attribute::ResponseOrigin(raw.try_into()?),
+            ATTR_OTHER_ADDRESS => Attribute::OtherAddress(raw.try_into()?),
+            _ => Attribute::decode(&raw.to_owned(), header)?,
+        };
+
+        Ok(attr)
+    }
+
+    pub fn attr_type(&self) -> u16 {
+        match self {
+            Attribute::MappedAddress(v)
+            | Attribute::SourceAddress(v)
+            | Attribute::ChangedAddress(v)
+            | Attribute::ResponseOrigin(v)
+            | Attribute::OtherAddress(v)
+            | Attribute::AlternateServer(v) => v.attr_type,
+            Attribute::ChangeRequest(_) => ATTR_CHANGE_REQUEST,
+            Attribute::Username(_) => ATTR_USERNAME,
+            Attribute::MessageIntegrity(_) => ATTR_MESSAGE_INTEGRITY,
+            Attribute::ErrorCode(_) => ATTR_ERROR_CODE,
+            Attribute::UnknownAttributes(_) => ATTR_UNKNOWN_ATTRIBUTES,
+            Attribute::Realm(_) => ATTR_REALM,
+            Attribute::Nonce(_) => ATTR_NONCE,
+            Attribute::MessageIntegritySha256(_) => ATTR_MESSAGE_INTEGRITY_SHA256,
+            Attribute::PasswordAlgorithm(_) => ATTR_PASSWORD_ALGORITHM,
+            Attribute::Userhash(_) => ATTR_USERHASH,
+            Attribute::Padding(_) => ATTR_PADDING,
+            Attribute::ResponsePort(_) => ATTR_RESPONSE_PORT,
+            Attribute::PasswordAlgorithms(_) => ATTR_PASSWORD_ALGORITHMS,
+            Attribute::XorMappedAddress(_) => ATTR_XOR_MAPPED_ADDRESS,
+            Attribute::Software(_) => ATTR_SOFTWARE,
+            Attribute::Fingerprint(_) => ATTR_FINGERPRINT,
+            Attribute::Custom(v) => v.raw.attr_type,
+            Attribute::Unknown(v) => v.attr_type,
+        }
+    }
+
+    // 4 + value + padding
+    pub fn len_with(&self, padding: AttrPadding) -> usize {
+        let value_len = self.value_len();
+        4 + value_len + padding.padding_len(value_len)
+    }
+
+    // 直接写到 buf 的后面, 不经过 RawAttr, 返回写入的长度 (包括 padding)
+    // 不认识的 attribute 保留收到的 padding
+    pub fn encode<B: BufMut>(&self, buf: &mut B, padding: AttrPadding) -> usize {
+        match self {
+            Attribute::Custom(v) => v.raw.encode(buf, padding),
+            Attribute::Unknown(v) => v.encode(buf, padding),
+            _ => {
+                let value_len = self.value_len();
+                buf.put_u16(self.attr_type());
+                buf.put_u16(value_len as u16);
+                self.encode_value(buf);
+                buf.put_bytes(0, padding.padding_len(value_len));
+                self.len_with(padding)
+            }
+        }
+    }
+
+    fn value_len(&self) -> usize {
+        match self {
+            Attribute::MappedAddress(v)
+            | Attribute::SourceAddress(v)
+            | Attribute::ChangedAddress(v)
+            | Attribute::ResponseOrigin(v)
+            | Attribute::OtherAddress(v)
+            | Attribute::AlternateServer(v) => v.value_len(),
+            Attribute::ChangeRequest(v) => v.value_len(),
+            Attribute::Username(v) => v.value_len(),
+            Attribute::MessageIntegrity(v) => v.value_len(),
+            Attribute::ErrorCode(v) => v.value_len(),
+            Attribute::UnknownAttributes(v) => v.value_len(),
+            Attribute::Realm(v) => v.value_len(),
+            Attribute::Nonce(v) => v.value_len(),
+            Attribute::MessageIntegritySha256(v) => v.value_len(),
+            Attribute::PasswordAlgorithm(v) => v.value_len(),
+            Attribute::Userhash(v) => v.value_len(),
+            Attribute::Padding(v) => v.value_len(),
+            Attribute::ResponsePort(v) => v.value_len(),
+            Attribute::PasswordAlgorithms(v) => v.value_len(),
+            Attribute::XorMappedAddress(v) => v.value_len(),
+            Attribute::Software(v) => v.value_len(),
+            Attribute::Fingerprint(v) => v.value_len(),
+            Attribute::Custom(v) => v.raw.value.len(),
+            Attribute::Unknown(v) => v.value.len(),
+        }
+    }
+
+    fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        match self {
+            Attribute::MappedAddress(v)
+            | Attribute::SourceAddress(v)
+            | Attribute::ChangedAddress(v)
+            | Attribute::ResponseOrigin(v)
+            | Attribute::OtherAddress(v)
+            | Attribute::AlternateServer(v) => v.encode_value(buf),
+            Attribute::ChangeRequest(v) => v.encode_value(buf),
+            Attribute::Username(v) => v.encode_value(buf),
+            Attribute::MessageIntegrity(v) => v.encode_value(buf),
+            Attribute::ErrorCode(v) => v.encode_value(buf),
+            Attribute::UnknownAttributes(v) => v.encode_value(buf),
+            Attribute::Realm(v) => v.encode_value(buf),
+            Attribute::Nonce(v) => v.encode_value(buf),
+            Attribute::MessageIntegritySha256(v) => v.encode_value(buf),
+            Attribute::PasswordAlgorithm(v) => v.encode_value(buf),
+            Attribute::Userhash(v) => v.encode_value(buf),
+            Attribute::Padding(v) => v.encode_value(buf),
+            Attribute::ResponsePort(v) => v.encode_value(buf),
+            Attribute::PasswordAlgorithms(v) => v.encode_value(buf),
+            Attribute::XorMappedAddress(v) => v.encode_value(buf),
+            Attribute::Software(v) => v.encode_value(buf),
+            Attribute::Fingerprint(v) => v.encode_value(buf),
+            Attribute::Custom(v) => buf.put_slice(&v.raw.value),
+            Attribute::Unknown(v) => buf.put_slice(&v.value),
+        }
+    }
+
+    // Display 和 serde 输出的名字
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Attribute::Custom(v) => v.name(),
+            _ => Attribute::name(self.attr_type()).unwrap_or("UNKNOWN"),
+        }
+    }
+}
+
+impl From<Attribute> for RawAttr {
+    fn from(attr: Attribute) -> Self {
+        match attr {
+            Attribute::MappedAddress(v)
+            | Attribute::SourceAddress(v)
+            | Attribute::ChangedAddress(v)
+            | Attribute::ResponseOrigin(v)
+            | Attribute::OtherAddress(v)
+            | Attribute::AlternateServer(v) => v.into(),
+            Attribute::ChangeRequest(v) => v.into(),
+            Attribute::Username(v) => v.into(),
+            Attribute::MessageIntegrity(v) => v.into(),
+            Attribute::ErrorCode(v) => v.into(),
+            Attribute::UnknownAttributes(v) => v.into(),
+            Attribute::Realm(v) => v.into(),
+            Attribute::Nonce(v) => v.into(),
+            Attribute::MessageIntegritySha256(v) => v.into(),
+            Attribute::PasswordAlgorithm(v) => v.into(),
+            Attribute::Userhash(v) => v.into(),
+            Attribute::Padding(v) => v.into(),
+            Attribute::ResponsePort(v) => v.into(),
+            Attribute::PasswordAlgorithms(v) => v.into(),
+            Attribute::XorMappedAddress(v) => v.into(),
+            Attribute::Software(v) => v.into(),
+            Attribute::Fingerprint(v) => v.into(),
+            Attribute::Custom(v) => v.raw,
+            Attribute::Unknown(v) => v,
+        }
+    }
+}
+
+impl AttrValidator for Attribute {
+    fn validate(&self) -> Option<ValidateErr> {
+        match self {
+            Attribute::MappedAddress(v)
+            | Attribute::SourceAddress(v)
+            | Attribute::ChangedAddress(v)
+            | Attribute::ResponseOrigin(v)
+            | Attribute::OtherAddress(v)
+            | Attribute::AlternateServer(v) => v.validate(),
+            Attribute::ChangeRequest(v) => v.validate(),
+            Attribute::Username(v) => v.validate(),
+            Attribute::MessageIntegrity(v) => v.validate(),
+            Attribute::ErrorCode(v) => v.validate(),
+            Attribute::UnknownAttributes(v) => v.validate(),
+            Attribute::Realm(v) => v.validate(),
+            Attribute::Nonce(v) => v.validate(),
+            Attribute::MessageIntegritySha256(v) => v.validate(),
+            Attribute::PasswordAlgorithm(v) => v.validate(),
+            Attribute::Userhash(v) => v.validate(),
+            Attribute::Padding(v) => v.validate(),
+            Attribute::ResponsePort(v) => v.validate(),
+            Attribute::PasswordAlgorithms(v) => v.validate(),
+            Attribute::XorMappedAddress(v) => v.validate(),
+            Attribute::Software(v) => v.validate(),
+            Attribute::Fingerprint(v) => v.validate(),
+            Attribute::Custom(v) => v.validate(),
+            Attribute::Unknown(_) => None,
+        }
+    }
+}
+
+// XOR-MAPPED-ADDRESS (0x0020): 1.2.3.4:5678
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attribute::Unknown(v) => write!(f, "{}", v),
+            _ => {
+                write!(f, "{} ({:#06x}): ", self.type_name(), self.attr_type())?;
+                self.fmt_value(f)
+            }
+        }
+    }
+}
+
+impl Attribute {
+    // 只输出解析后的 value
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attribute::MappedAddress(v)
+            | Attribute::SourceAddress(v)
+            | Attribute::ChangedAddress(v)
+            | Attribute::ResponseOrigin(v)
+            | Attribute::OtherAddress(v)
+            | Attribute::AlternateServer(v) => write!(f, "{}", v),
+            Attribute::ChangeRequest(v) => write!(f, "{}", v),
+            Attribute::Username(v) => write!(f, "{}", v),
+            Attribute::MessageIntegrity(v) => write!(f, "{}", v),
+            Attribute::ErrorCode(v) => write!(f, "{}", v),
+            Attribute::UnknownAttributes(v) => write!(f, "{}", v),
+            Attribute::Realm(v) => write!(f, "{}", v),
+            Attribute::Nonce(v) => write!(f, "{}", v),
+            Attribute::MessageIntegritySha256(v) => write!(f, "{}", v),
+            Attribute::PasswordAlgorithm(v) => write!(f, "{}", v),
+            Attribute::Userhash(v) => write!(f, "{}", v),
+            Attribute::Padding(v) => write!(f, "{}", v),
+            Attribute::ResponsePort(v) => write!(f, "{}", v),
+            Attribute::PasswordAlgorithms(v) => write!(f, "{}", v),
+            Attribute::XorMappedAddress(v) => write!(f, "{}", v),
+            Attribute::Software(v) => write!(f, "{}", v),
+            Attribute::Fingerprint(v) => write!(f, "{}", v),
+            Attribute::Custom(v) => write!(f, "{}", v),
+            Attribute::Unknown(v) => write!(f, "{}", util::Hex(&v.value)),
+        }
+    }
+
+    // Packet 和 PacketRef 的 Display 使用, 一行一个 attribute
+    // 解析失败的输出 hex 和错误
+    pub(crate) fn fmt_line(
+        f: &mut fmt::Formatter<'_>,
+        raw: RawAttrRef<'_>,
+        header: &Header,
+        registry: &AttrRegistry,
+    ) -> fmt::Result {
+        let attr = match registry.contains(raw.attr_type) {
+            true => Attribute::decode_with(&raw.to_owned(), header, registry),
+            false => Attribute::decode_ref(raw, header),
+        };
+
+        match attr {
+            Ok(Attribute::Unknown(_)) => write!(f, "{}", raw),
+            Ok(v) => {
+                write!(
+                    f,
+                    "{} ({:#06x}), {} bytes: ",
+                    v.type_name(),
+                    raw.attr_type,
+                    raw.value.len()
+                )?;
+                v.fmt_value(f)
+            }
+            Err(e) => write!(f, "{} (error: {})", raw, e),
+        }
+    }
+}
+
+//--------------------------------------------------
+
+// 只有一个 attr_type 的 attribute, 直接使用 TryFrom<RawAttr>
+macro_rules! impl_typed_attr {
+    ($t:ty, $attr_type:expr) => {
+        impl TypedAttr for $t {
+            fn is_type(attr_type: u16) -> bool {
+                attr_type == $attr_type
+            }
+
+            fn value_len(&self) -> usize {
+                <$t>::value_len(self)
+            }
+
+            fn encode_value<B: BufMut>(&self, buf: &mut B) {
+                <$t>::encode_value(self, buf)
+            }
+
+            fn decode(raw: &RawAttr, _header: &Header) -> Result<Self, ParsePacketErr> {
+                raw.clone().try_into()
+            }
+        }
+    };
+}
+
+// 可以直接从借用的 buf 解析, 不需要分配内存
+macro_rules! impl_typed_attr_ref {
+    ($t:ty, $attr_type:expr) => {
+        impl TypedAttr for $t {
+            fn is_type(attr_type: u16) -> bool {
+                attr_type == $attr_type
+            }
+
+            fn value_len(&self) -> usize {
+                <$t>::value_len(self)
+            }
+
+            fn encode_value<B: BufMut>(&self, buf: &mut B) {
+                <$t>::encode_value(self, buf)
+            }
+
+            fn decode(raw: &RawAttr, _header: &Header) -> Result<Self, ParsePacketErr> {
+                raw.clone().try_into()
+            }
+
+            fn decode_ref(raw: RawAttrRef<'_>, _header: &Header) -> Result<Self, ParsePacketErr> {
+                raw.try_into()
+            }
+        }
+    };
+}
+
+impl_typed_attr_ref!(ChangeRequest, ATTR_CHANGE_REQUEST);
+impl_typed_attr_ref!(MessageIntegrity, ATTR_MESSAGE_INTEGRITY);
+impl_typed_attr_ref!(Userhash, ATTR_USERHASH);
+impl_typed_attr_ref!(ResponsePort, ATTR_RESPONSE_PORT);
+impl_typed_attr_ref!(Fingerprint, ATTR_FINGERPRINT);
+impl_typed_attr!(Username, ATTR_USERNAME);
+impl_typed_attr!(ErrcodeAttr, ATTR_ERROR_CODE);
+impl_typed_attr!(UnknownAttributes, ATTR_UNKNOWN_ATTRIBUTES);
+impl_typed_attr!(Realm, ATTR_REALM);
+impl_typed_attr!(Nonce, ATTR_NONCE);
+impl_typed_attr!(MessageIntegritySha256, ATTR_MESSAGE_INTEGRITY_SHA256);
+impl_typed_attr!(PasswordAlgorithm, ATTR_PASSWORD_ALGORITHM);
+impl_typed_attr!(PaddingAttr, ATTR_PADDING);
+impl_typed_attr!(PasswordAlgorithms, ATTR_PASSWORD_ALGORITHMS);
+impl_typed_attr!(Software, ATTR_SOFTWARE);
+
+// mapped-address, source-address, changed-address, response-origin, other-address
+// 指定具体类型时使用 Packet::get_by_type
+impl TypedAttr for AddressAttr {
+    fn is_type(attr_type: u16) -> bool {
+        AddressAttr::is_like_mapped_addr(attr_type)
+    }
+
+    fn value_len(&self) -> usize {
+        AddressAttr::value_len(self)
+    }
+
+    fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        AddressAttr::encode_value(self, buf)
+    }
+
+    fn decode(raw: &RawAttr, _header: &Header) -> Result<Self, ParsePacketErr> {
+        raw.clone().try_into()
+    }
+
+    fn decode_ref(raw: RawAttrRef<'_>, _header: &Header) -> Result<Self, ParsePacketErr> {
+        raw.try_into()
+    }
+}
+
+impl TypedAttr for XorMappedAddress {
+    fn is_type(attr_type: u16) -> bool {
+        attr_type == ATTR_XOR_MAPPED_ADDRESS
+    }
+
+    fn value_len(&self) -> usize {
+        XorMappedAddress::value_len(self)
+    }
+
+    fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        XorMappedAddress::encode_value(self, buf)
+    }
+
+    fn decode(raw: &RawAttr, header: &Header) -> Result<Self, ParsePacketErr> {
+        Self::decode_ref(raw.into(), header)
+    }
+
+    fn decode_ref(raw: RawAttrRef<'_>, header: &Header) -> Result<Self, ParsePacketErr> {
+        XorMappedAddress::from_attr_ref(raw, header)
+    }
+}
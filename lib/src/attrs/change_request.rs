@@ -1,10 +1,11 @@
-use crate::attrs::RawAttr;
+use crate::attrs::{RawAttr, RawAttrRef};
 use crate::constants::*;
 use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
-use bytes::{BufMut, BytesMut};
-use std::ops::Deref;
+use bytes::BufMut;
+use core::fmt;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChangeRequest {
     pub change_ip: bool,
     pub change_port: bool,
@@ -19,19 +20,26 @@ impl ChangeRequest {
     }
 }
 
-impl From<ChangeRequest> for RawAttr {
-    fn from(attr: ChangeRequest) -> Self {
+impl ChangeRequest {
+    pub fn value_len(&self) -> usize {
+        4
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
         let mut flag: u32 = 0;
-        if attr.change_ip {
+        if self.change_ip {
             flag |= 0x04;
         }
-        if attr.change_port {
+        if self.change_port {
             flag |= 0x02;
         }
-        let mut bytes_buf = BytesMut::with_capacity(4);
-        bytes_buf.put_u32(flag);
-        let value = bytes_buf.freeze();
-        RawAttr::new(ATTR_CHANGE_REQUEST, value)
+        buf.put_u32(flag);
+    }
+}
+
+impl From<ChangeRequest> for RawAttr {
+    fn from(attr: ChangeRequest) -> Self {
+        RawAttr::from_typed(ATTR_CHANGE_REQUEST, &attr)
     }
 }
 
@@ -39,15 +47,24 @@ impl TryFrom<RawAttr> for ChangeRequest {
     type Error = ParsePacketErr;
 
     fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        RawAttrRef::from(&base_attr).try_into()
+    }
+}
+
+impl TryFrom<RawAttrRef<'_>> for ChangeRequest {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttrRef<'_>) -> Result<Self, Self::Error> {
         if base_attr.value.len() != 4 {
-            return Err(ParsePacketErr::BufSize(format!(
-                "change_request attr len:{} !=4",
-                base_attr.value.len()
-            )));
+            return Err(ParsePacketErr::AttrLen {
+                attr_type: base_attr.attr_type,
+                expected: 4,
+                actual: base_attr.value.len(),
+            });
         }
 
-        let value = base_attr.value.deref();
-        let flag = value[4];
+        let value = base_attr.value;
+        let flag = value[3];
 
         let change_ip = flag & 0x04 == 0x04;
         let change_port = flag & 0x02 == 0x02;
@@ -63,3 +80,13 @@ impl AttrValidator for ChangeRequest {
         None
     }
 }
+
+impl fmt::Display for ChangeRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "change ip: {}, change port: {}",
+            self.change_ip, self.change_port
+        )
+    }
+}
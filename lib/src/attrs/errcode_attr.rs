@@ -1,15 +1,18 @@
-use std::ops::Deref;
+use alloc::string::{String, ToString};
+use core::ops::Deref;
 
 use crate::attrs::RawAttr;
 use crate::constants::ATTR_ERROR_CODE;
 use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
 use crate::util;
-use bytes::{BufMut, BytesMut};
+use bytes::BufMut;
+use core::fmt;
 
 // class:  3 bit        1-6
 // number: 8 bit        0-99
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ErrcodeAttr {
     pub code: u16,
     pub msg: String,
@@ -24,15 +27,27 @@ impl ErrcodeAttr {
     }
 }
 
+impl ErrcodeAttr {
+    // reason 用空格补齐到 4 字节
+    pub fn value_len(&self) -> usize {
+        4 + self.msg.len() + self.reason_padding()
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_u16(0);
+        buf.put_u16(util::pack_error_code(self.code));
+        buf.put_slice(self.msg.as_bytes());
+        buf.put_bytes(b' ', self.reason_padding());
+    }
+
+    fn reason_padding(&self) -> usize {
+        (4 - self.msg.len() % 4) % 4
+    }
+}
+
 impl From<ErrcodeAttr> for RawAttr {
     fn from(attr: ErrcodeAttr) -> Self {
-        let mut bytes_buf = BytesMut::with_capacity(4);
-        bytes_buf.put_u16(0);
-        bytes_buf.put_u16(util::pack_error_code(attr.code));
-        bytes_buf.put_slice(util::pack_error_reason(&attr.msg).as_bytes());
-
-        let value = bytes_buf.freeze();
-        RawAttr::new(ATTR_ERROR_CODE, value)
+        RawAttr::from_typed(ATTR_ERROR_CODE, &attr)
     }
 }
 
@@ -41,10 +56,11 @@ impl TryFrom<RawAttr> for ErrcodeAttr {
 
     fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
         if base_attr.value.len() < 4 {
-            return Err(ParsePacketErr::BufSize(format!(
-                "err_code attr buf len:{} < 4",
-                base_attr.value.len()
-            )));
+            return Err(ParsePacketErr::AttrLen {
+                attr_type: base_attr.attr_type,
+                expected: 4,
+                actual: base_attr.value.len(),
+            });
         }
 
         // 从 value中解析
@@ -52,6 +68,13 @@ impl TryFrom<RawAttr> for ErrcodeAttr {
         let value = base_attr.value.deref();
 
         index += 2;
+        // number 超过 99 时重新编码会变成另一个错误码
+        if value[index + 1] > 99 {
+            return Err(ParsePacketErr::BadAttrValue {
+                attr_type: base_attr.attr_type,
+                reason: "error code number > 99",
+            });
+        }
         let code = u16::from_be_bytes([value[index], value[index + 1]]);
         let code = util::unpack_error_code(code);
 
@@ -60,7 +83,9 @@ impl TryFrom<RawAttr> for ErrcodeAttr {
         let msg = match String::from_utf8(msg.into()) {
             Ok(v) => v.trim().to_string(),
             Err(_e) => {
-                return Err(ParsePacketErr::NotUtf8);
+                return Err(ParsePacketErr::NotUtf8 {
+                    attr_type: base_attr.attr_type,
+                });
             }
         };
 
@@ -73,7 +98,12 @@ impl AttrValidator for ErrcodeAttr {
             return None;
         }
 
-        let err_msg = format!("wrong code: {}", self.code);
-        Some(ValidateErr(err_msg))
+        Some(ValidateErr::BadErrorCode(self.code))
+    }
+}
+
+impl fmt::Display for ErrcodeAttr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.code, self.msg)
     }
 }
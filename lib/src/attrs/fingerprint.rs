@@ -0,0 +1,84 @@
+use crate::attrs::{RawAttr, RawAttrRef};
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use bytes::BufMut;
+use core::fmt;
+
+// rfc 5389, 15.5
+// CRC-32(header + fingerprint 之前的所有 attribute) ^ 0x5354554e
+// 必须是最后一个 attribute, header 的 msg_len 需要包括 fingerprint 本身
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Fingerprint {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex_u32"))]
+    pub crc: u32,
+}
+
+impl Fingerprint {
+    pub fn new(crc: u32) -> Self {
+        Self { crc }
+    }
+
+    // data: header + fingerprint 之前的 attribute
+    pub fn calculate(data: &[u8]) -> Self {
+        Self {
+            crc: crc32fast::hash(data) ^ FINGERPRINT_XOR,
+        }
+    }
+}
+
+impl Fingerprint {
+    pub fn value_len(&self) -> usize {
+        FINGERPRINT_LEN
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_u32(self.crc);
+    }
+}
+
+impl From<Fingerprint> for RawAttr {
+    fn from(attr: Fingerprint) -> Self {
+        RawAttr::from_typed(ATTR_FINGERPRINT, &attr)
+    }
+}
+
+impl TryFrom<RawAttr> for Fingerprint {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        RawAttrRef::from(&base_attr).try_into()
+    }
+}
+
+impl TryFrom<RawAttrRef<'_>> for Fingerprint {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttrRef<'_>) -> Result<Self, Self::Error> {
+        if base_attr.value.len() != FINGERPRINT_LEN {
+            return Err(ParsePacketErr::AttrLen {
+                attr_type: base_attr.attr_type,
+                expected: FINGERPRINT_LEN,
+                actual: base_attr.value.len(),
+            });
+        }
+
+        let value = base_attr.value;
+        let crc = u32::from_be_bytes([value[0], value[1], value[2], value[3]]);
+
+        Ok(Self { crc })
+    }
+}
+
+impl AttrValidator for Fingerprint {
+    fn validate(&self) -> Option<ValidateErr> {
+        None
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010x}", self.crc)
+    }
+}
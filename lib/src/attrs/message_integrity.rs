@@ -0,0 +1,78 @@
+use crate::attrs::{RawAttr, RawAttrRef};
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use crate::util;
+use bytes::{BufMut, Bytes};
+use core::fmt;
+
+// rfc 5389, 15.4
+// HMAC-SHA1, 20 bytes
+// 计算范围: header (msg_len 包括 message-integrity 自己) + message-integrity 之前的所有 attribute
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MessageIntegrity {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex"))]
+    pub hmac: [u8; MESSAGE_INTEGRITY_LEN],
+}
+
+impl MessageIntegrity {
+    pub fn new(hmac: [u8; MESSAGE_INTEGRITY_LEN]) -> Self {
+        Self { hmac }
+    }
+}
+
+impl MessageIntegrity {
+    pub fn value_len(&self) -> usize {
+        MESSAGE_INTEGRITY_LEN
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_slice(&self.hmac);
+    }
+}
+
+impl From<MessageIntegrity> for RawAttr {
+    fn from(attr: MessageIntegrity) -> Self {
+        RawAttr::new(ATTR_MESSAGE_INTEGRITY, Bytes::copy_from_slice(&attr.hmac))
+    }
+}
+
+impl TryFrom<RawAttr> for MessageIntegrity {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        RawAttrRef::from(&base_attr).try_into()
+    }
+}
+
+impl TryFrom<RawAttrRef<'_>> for MessageIntegrity {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttrRef<'_>) -> Result<Self, Self::Error> {
+        if base_attr.value.len() != MESSAGE_INTEGRITY_LEN {
+            return Err(ParsePacketErr::AttrLen {
+                attr_type: base_attr.attr_type,
+                expected: MESSAGE_INTEGRITY_LEN,
+                actual: base_attr.value.len(),
+            });
+        }
+
+        let mut hmac = [0_u8; MESSAGE_INTEGRITY_LEN];
+        hmac.copy_from_slice(base_attr.value);
+
+        Ok(Self { hmac })
+    }
+}
+
+impl AttrValidator for MessageIntegrity {
+    fn validate(&self) -> Option<ValidateErr> {
+        None
+    }
+}
+
+impl fmt::Display for MessageIntegrity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", util::Hex(&self.hmac))
+    }
+}
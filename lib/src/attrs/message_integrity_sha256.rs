@@ -0,0 +1,71 @@
+use crate::attrs::RawAttr;
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use crate::util;
+use bytes::{BufMut, Bytes};
+use core::fmt;
+
+// rfc 8489, 14.6
+// HMAC-SHA256, 16 - 32 bytes, 4的倍数 (可以截断)
+// 计算范围和 message-integrity 一样
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MessageIntegritySha256 {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex"))]
+    pub hmac: Bytes,
+}
+
+impl MessageIntegritySha256 {
+    pub fn new(hmac: Bytes) -> Self {
+        Self { hmac }
+    }
+}
+
+impl MessageIntegritySha256 {
+    pub fn value_len(&self) -> usize {
+        self.hmac.len()
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_slice(&self.hmac);
+    }
+}
+
+impl From<MessageIntegritySha256> for RawAttr {
+    fn from(attr: MessageIntegritySha256) -> Self {
+        RawAttr::new(ATTR_MESSAGE_INTEGRITY_SHA256, attr.hmac)
+    }
+}
+
+impl TryFrom<RawAttr> for MessageIntegritySha256 {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        let len = base_attr.value.len();
+        if !(MESSAGE_INTEGRITY_SHA256_MIN_LEN..=MESSAGE_INTEGRITY_SHA256_LEN).contains(&len)
+            || !len.is_multiple_of(4)
+        {
+            return Err(ParsePacketErr::BadAttrValue {
+                attr_type: base_attr.attr_type,
+                reason: "hmac len must be 16..=32 and a multiple of 4",
+            });
+        }
+
+        Ok(Self {
+            hmac: base_attr.value,
+        })
+    }
+}
+
+impl AttrValidator for MessageIntegritySha256 {
+    fn validate(&self) -> Option<ValidateErr> {
+        None
+    }
+}
+
+impl fmt::Display for MessageIntegritySha256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", util::Hex(&self.hmac))
+    }
+}
@@ -1,21 +1,64 @@
 #![allow(clippy::len_without_is_empty)]
 
-use crate::error::ParsePacketErr;
+use crate::attrs::attribute::{Attribute, TypedAttr};
+use crate::constants::ATTR_COMPREHENSION_OPTIONAL_MIN;
+use crate::error::{ParsePacketErr, ValidateErr};
+use crate::util;
+use alloc::string::{String, ToString};
 use bytes::{BufMut, Bytes, BytesMut};
-use std::ops::Deref;
+use core::fmt;
+use core::ops::Deref;
 
 pub mod address_attr;
+pub mod attribute;
 pub mod change_request;
 pub mod errcode_attr;
+pub mod fingerprint;
+pub mod message_integrity;
+pub mod message_integrity_sha256;
+pub mod nonce;
 pub mod padding_attr;
+pub mod password_algorithm;
+pub mod realm;
+pub mod registry;
 pub mod response_port;
+pub mod software;
+pub mod unknown_attributes;
+pub mod userhash;
+pub mod username;
 pub mod xor_address;
 
+// rfc 5389, 15
+// attribute 需要 4 字节对齐, attr_len 是 value 的实际长度 (不包括 padding)
+// rfc 3489 的一些实现不做 padding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttrPadding {
+    #[default]
+    Padded,
+    Unpadded,
+}
+
+impl AttrPadding {
+    pub fn padding_len(&self, attr_len: usize) -> usize {
+        match self {
+            AttrPadding::Padded => (4 - attr_len % 4) % 4,
+            AttrPadding::Unpadded => 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RawAttr {
     pub attr_type: u16,
+
+    // value 的长度, 不包括 padding
     pub attr_len: u16,
     pub value: Bytes,
+
+    // rfc 5389, 15
+    // padding 可以是任意值, 收到的 padding 原样保留, 编码时写回,
+    // message-integrity / fingerprint 要按收到的字节计算. 为空时写 0
+    pub padding: Bytes,
 }
 
 impl RawAttr {
@@ -24,32 +67,80 @@ impl RawAttr {
             attr_type,
             attr_len: value.len() as u16,
             value,
+            padding: Bytes::new(),
+        }
+    }
+
+    pub fn with_padding(attr_type: u16, value: Bytes, padding: Bytes) -> Self {
+        Self {
+            padding,
+            ..Self::new(attr_type, value)
         }
     }
 
+    // 使用 TypedAttr::encode_value 编码 value
+    pub fn from_typed<T: TypedAttr>(attr_type: u16, attr: &T) -> Self {
+        let mut buf = BytesMut::with_capacity(attr.value_len());
+        attr.encode_value(&mut buf);
+        Self::new(attr_type, buf.freeze())
+    }
+
+    // rfc 5389, 15
+    // 不认识时, 请求要用 420 拒绝, 响应要丢弃
+    pub fn is_comprehension_required(&self) -> bool {
+        self.attr_type < ATTR_COMPREHENSION_OPTIONAL_MIN
+    }
+
+    // 4 + value + padding
     pub fn len(&self) -> usize {
-        self.attr_len as usize + 4
+        self.len_with(AttrPadding::Padded)
+    }
+
+    pub fn len_with(&self, padding: AttrPadding) -> usize {
+        let attr_len = self.attr_len as usize;
+        4 + attr_len + padding.padding_len(attr_len)
+    }
+
+    pub fn padding_len(&self) -> usize {
+        AttrPadding::Padded.padding_len(self.attr_len as usize)
     }
 
     pub fn pack(&self) -> Bytes {
-        let buf_len = self.attr_len + 4;
-        let mut buf = BytesMut::with_capacity(buf_len as usize);
+        self.pack_with(AttrPadding::Padded)
+    }
 
+    pub fn pack_with(&self, padding: AttrPadding) -> Bytes {
+        let mut buf = BytesMut::with_capacity(self.len_with(padding));
+        self.encode(&mut buf, padding);
+        buf.freeze()
+    }
+
+    // 写到 buf 的后面, 返回写入的长度 (包括 padding)
+    pub fn encode<B: BufMut>(&self, buf: &mut B, padding: AttrPadding) -> usize {
         buf.put_u16(self.attr_type);
         buf.put_u16(self.attr_len);
         buf.put_slice(&self.value);
+        buf.put_slice(self.padding_bytes(padding));
+        self.len_with(padding)
+    }
 
-        buf.freeze()
+    // 收到的 padding 长度一致时原样使用, 否则补 0
+    pub(crate) fn padding_bytes(&self, padding: AttrPadding) -> &[u8] {
+        let padding_len = padding.padding_len(self.attr_len as usize);
+        match self.padding.len() == padding_len {
+            true => &self.padding,
+            false => &[0_u8; 3][..padding_len],
+        }
     }
 
     pub fn unpack(buf_bytes: Bytes) -> Result<Self, ParsePacketErr> {
         let buf = buf_bytes.deref();
 
         if buf.len() < 4 {
-            return Err(ParsePacketErr::BufSize(format!(
-                "attr buf len:{}",
-                buf.len()
-            )));
+            return Err(ParsePacketErr::BufSize {
+                expected: 4,
+                actual: buf.len(),
+            });
         }
 
         let mut index = 0_usize;
@@ -58,12 +149,13 @@ impl RawAttr {
         index += 2;
         let attr_len = u16::from_be_bytes([buf[index], buf[index + 1]]);
 
-        if buf.len() < (attr_len + 4) as usize {
-            return Err(ParsePacketErr::BufSize(format!(
-                "attr buf len:{} < {}",
-                buf.len(),
-                attr_len + 4
-            )));
+        if buf.len() < attr_len as usize + 4 {
+            return Err(ParsePacketErr::AttrTruncated {
+                attr_type,
+                offset: 0,
+                expected: attr_len as usize + 4,
+                actual: buf.len(),
+            });
         }
 
         index += 2;
@@ -72,10 +164,111 @@ impl RawAttr {
 
         let value = value.freeze();
 
-        Ok(Self {
+        Ok(Self::new(attr_type, value))
+    }
+}
+
+// 借用接收 buf 的 attribute, 不复制 value
+#[derive(Debug, Clone, Copy)]
+pub struct RawAttrRef<'a> {
+    pub attr_type: u16,
+
+    // 不包括 padding
+    pub value: &'a [u8],
+
+    // 收到的 padding, 没有 padding 时为空
+    pub padding: &'a [u8],
+}
+
+impl<'a> RawAttrRef<'a> {
+    pub fn new(attr_type: u16, value: &'a [u8]) -> Self {
+        Self {
             attr_type,
-            attr_len,
             value,
-        })
+            padding: &[],
+        }
+    }
+
+    pub fn with_padding(attr_type: u16, value: &'a [u8], padding: &'a [u8]) -> Self {
+        Self {
+            attr_type,
+            value,
+            padding,
+        }
+    }
+
+    pub fn is_comprehension_required(&self) -> bool {
+        self.attr_type < ATTR_COMPREHENSION_OPTIONAL_MIN
+    }
+
+    pub fn len_with(&self, padding: AttrPadding) -> usize {
+        4 + self.value.len() + padding.padding_len(self.value.len())
+    }
+
+    pub fn to_owned(&self) -> RawAttr {
+        RawAttr::with_padding(
+            self.attr_type,
+            Bytes::copy_from_slice(self.value),
+            Bytes::copy_from_slice(self.padding),
+        )
+    }
+}
+
+impl<'a> From<&'a RawAttr> for RawAttrRef<'a> {
+    fn from(attr: &'a RawAttr) -> Self {
+        Self::with_padding(attr.attr_type, attr.value.deref(), attr.padding.deref())
     }
 }
+
+// 不认识或者解析失败的 attribute, value 输出 hex
+impl fmt::Display for RawAttr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", RawAttrRef::from(self))
+    }
+}
+
+// SOFTWARE (0x8022), 12 bytes: 736572766572...
+impl fmt::Display for RawAttrRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({:#06x}), {} bytes: {}",
+            Attribute::name(self.attr_type).unwrap_or("UNKNOWN"),
+            self.attr_type,
+            self.value.len(),
+            util::Hex(self.value)
+        )
+    }
+}
+
+// realm, nonce, software 的 value 是 utf-8 字符串
+pub(crate) fn decode_text(raw: &RawAttr) -> Result<String, ParsePacketErr> {
+    match core::str::from_utf8(&raw.value) {
+        Ok(v) => Ok(v.to_string()),
+        Err(_e) => Err(ParsePacketErr::NotUtf8 {
+            attr_type: raw.attr_type,
+        }),
+    }
+}
+
+// 少于 128 个字符, 并且不超过 max_len 字节
+pub(crate) fn validate_text(attr_type: u16, text: &str, max_len: usize) -> Option<ValidateErr> {
+    if text.len() > max_len {
+        return Some(ValidateErr::TooLong {
+            attr_type,
+            len: text.len(),
+            max: max_len,
+        });
+    }
+
+    let chars = text.chars().count();
+    if chars >= 128 {
+        return Some(ValidateErr::TooLong {
+            attr_type,
+            len: chars,
+            max: 127,
+        });
+    }
+
+    None
+}
@@ -0,0 +1,60 @@
+use crate::attrs::{self, RawAttr};
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use alloc::string::{String, ToString};
+use bytes::{BufMut, Bytes};
+use core::fmt;
+
+// rfc 5389, 15.8
+// < 128 characters (763 bytes)
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Nonce {
+    pub nonce: String,
+}
+
+impl Nonce {
+    pub fn new(nonce: &str) -> Self {
+        Self {
+            nonce: nonce.to_string(),
+        }
+    }
+}
+
+impl Nonce {
+    pub fn value_len(&self) -> usize {
+        self.nonce.len()
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_slice(self.nonce.as_bytes());
+    }
+}
+
+impl From<Nonce> for RawAttr {
+    fn from(attr: Nonce) -> Self {
+        RawAttr::new(ATTR_NONCE, Bytes::from(attr.nonce.into_bytes()))
+    }
+}
+
+impl TryFrom<RawAttr> for Nonce {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        let nonce = attrs::decode_text(&base_attr)?;
+        Ok(Self { nonce })
+    }
+}
+
+impl AttrValidator for Nonce {
+    fn validate(&self) -> Option<ValidateErr> {
+        attrs::validate_text(ATTR_NONCE, &self.nonce, NONCE_MAX_LEN)
+    }
+}
+
+impl fmt::Display for Nonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.nonce)
+    }
+}
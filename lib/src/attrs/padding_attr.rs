@@ -1,11 +1,14 @@
 use crate::attrs::RawAttr;
 use crate::constants::ATTR_PADDING;
 use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
-use bytes::Bytes;
+use bytes::{BufMut, Bytes};
+use core::fmt;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PaddingAttr {
     // 4 * 2 * n , 4字节的偶数倍数
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex"))]
     pub data: Bytes,
 }
 
@@ -16,6 +19,16 @@ impl PaddingAttr {
     }
 }
 
+impl PaddingAttr {
+    pub fn value_len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_slice(&self.data);
+    }
+}
+
 impl From<PaddingAttr> for RawAttr {
     fn from(attr: PaddingAttr) -> Self {
         RawAttr::new(ATTR_PADDING, attr.data)
@@ -26,11 +39,11 @@ impl TryFrom<RawAttr> for PaddingAttr {
     type Error = ParsePacketErr;
 
     fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
-        if base_attr.value.len() % 8 != 0 {
-            return Err(ParsePacketErr::BadValue(format!(
-                "padding attr buf len:{}",
-                base_attr.value.len()
-            )));
+        if !base_attr.value.len().is_multiple_of(8) {
+            return Err(ParsePacketErr::BadAttrValue {
+                attr_type: base_attr.attr_type,
+                reason: "value len is not a multiple of 8",
+            });
         }
 
         Ok(Self {
@@ -44,3 +57,9 @@ impl AttrValidator for PaddingAttr {
         None
     }
 }
+
+impl fmt::Display for PaddingAttr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes", self.data.len())
+    }
+}
@@ -0,0 +1,186 @@
+use crate::attrs::RawAttr;
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use crate::util;
+use alloc::vec;
+use alloc::vec::Vec;
+use bytes::{BufMut, Bytes};
+use core::fmt;
+use core::ops::Deref;
+
+// rfc 8489, 14.11 / 14.12
+//
+// password-algorithm:  algorithm(2) + params len(2) + params (4字节对齐)
+// password-algorithms: 多个 password-algorithm 连在一起
+//
+// md5 和 sha256 都没有 params
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PasswordAlgorithm {
+    pub algorithm: u16,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex"))]
+    pub params: Bytes,
+}
+
+impl PasswordAlgorithm {
+    pub fn new(algorithm: u16) -> Self {
+        Self {
+            algorithm,
+            params: Bytes::new(),
+        }
+    }
+
+    // algorithm, length, params, params 的 padding
+    pub fn value_len(&self) -> usize {
+        4 + self.params.len() + (4 - self.params.len() % 4) % 4
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_u16(self.algorithm);
+        buf.put_u16(self.params.len() as u16);
+        buf.put_slice(&self.params);
+
+        let padding = (4 - self.params.len() % 4) % 4;
+        buf.put_bytes(0, padding);
+    }
+
+    // 返回解析出来的 algorithm, 以及占用的字节数
+    // attr_type 用于出错时说明是哪个 attribute
+    fn unpack_from(buf: &[u8], attr_type: u16) -> Result<(Self, usize), ParsePacketErr> {
+        if buf.len() < 4 {
+            return Err(ParsePacketErr::AttrLen {
+                attr_type,
+                expected: 4,
+                actual: buf.len(),
+            });
+        }
+
+        let algorithm = u16::from_be_bytes([buf[0], buf[1]]);
+        let params_len = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+
+        if buf.len() < 4 + params_len {
+            return Err(ParsePacketErr::AttrLen {
+                attr_type,
+                expected: 4 + params_len,
+                actual: buf.len(),
+            });
+        }
+
+        let params = Bytes::copy_from_slice(&buf[4..4 + params_len]);
+        let used = (4 + params_len).div_ceil(4) * 4;
+
+        Ok((Self { algorithm, params }, used.min(buf.len())))
+    }
+}
+
+impl From<PasswordAlgorithm> for RawAttr {
+    fn from(attr: PasswordAlgorithm) -> Self {
+        RawAttr::from_typed(ATTR_PASSWORD_ALGORITHM, &attr)
+    }
+}
+
+impl TryFrom<RawAttr> for PasswordAlgorithm {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        let (attr, _) =
+            PasswordAlgorithm::unpack_from(base_attr.value.deref(), base_attr.attr_type)?;
+        Ok(attr)
+    }
+}
+
+impl AttrValidator for PasswordAlgorithm {
+    fn validate(&self) -> Option<ValidateErr> {
+        if self.algorithm == PASSWORD_ALGORITHM_MD5 || self.algorithm == PASSWORD_ALGORITHM_SHA256 {
+            return None;
+        }
+
+        Some(ValidateErr::BadPasswordAlgorithm(self.algorithm))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PasswordAlgorithms {
+    pub algorithms: Vec<PasswordAlgorithm>,
+}
+
+impl PasswordAlgorithms {
+    pub fn new(algorithms: Vec<PasswordAlgorithm>) -> Self {
+        Self { algorithms }
+    }
+
+    pub fn contains(&self, algorithm: u16) -> bool {
+        self.algorithms.iter().any(|x| x.algorithm == algorithm)
+    }
+
+    pub fn value_len(&self) -> usize {
+        self.algorithms.iter().map(|x| x.value_len()).sum()
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        for v in self.algorithms.iter() {
+            v.encode_value(buf);
+        }
+    }
+}
+
+impl From<PasswordAlgorithms> for RawAttr {
+    fn from(attr: PasswordAlgorithms) -> Self {
+        RawAttr::from_typed(ATTR_PASSWORD_ALGORITHMS, &attr)
+    }
+}
+
+impl TryFrom<RawAttr> for PasswordAlgorithms {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        let mut buf = base_attr.value.deref();
+        let mut algorithms = vec![];
+
+        while !buf.is_empty() {
+            let (attr, used) = PasswordAlgorithm::unpack_from(buf, base_attr.attr_type)?;
+            algorithms.push(attr);
+            buf = &buf[used..];
+        }
+
+        Ok(Self { algorithms })
+    }
+}
+
+impl AttrValidator for PasswordAlgorithms {
+    fn validate(&self) -> Option<ValidateErr> {
+        if self.algorithms.is_empty() {
+            return Some(ValidateErr::EmptyPasswordAlgorithms);
+        }
+        None
+    }
+}
+
+// MD5 / SHA-256, 不认识的输出编号
+impl fmt::Display for PasswordAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.algorithm {
+            PASSWORD_ALGORITHM_MD5 => write!(f, "MD5")?,
+            PASSWORD_ALGORITHM_SHA256 => write!(f, "SHA-256")?,
+            v => write!(f, "{:#06x}", v)?,
+        }
+        if !self.params.is_empty() {
+            write!(f, " ({})", util::Hex(&self.params))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PasswordAlgorithms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, v) in self.algorithms.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", v)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use crate::attrs::{self, RawAttr};
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use alloc::string::{String, ToString};
+use bytes::{BufMut, Bytes};
+use core::fmt;
+
+// rfc 5389, 15.7
+// utf-8, < 128 characters (763 bytes)
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Realm {
+    pub realm: String,
+}
+
+impl Realm {
+    pub fn new(realm: &str) -> Self {
+        Self {
+            realm: realm.to_string(),
+        }
+    }
+}
+
+impl Realm {
+    pub fn value_len(&self) -> usize {
+        self.realm.len()
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_slice(self.realm.as_bytes());
+    }
+}
+
+impl From<Realm> for RawAttr {
+    fn from(attr: Realm) -> Self {
+        RawAttr::new(ATTR_REALM, Bytes::from(attr.realm.into_bytes()))
+    }
+}
+
+impl TryFrom<RawAttr> for Realm {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        let realm = attrs::decode_text(&base_attr)?;
+        Ok(Self { realm })
+    }
+}
+
+impl AttrValidator for Realm {
+    fn validate(&self) -> Option<ValidateErr> {
+        attrs::validate_text(ATTR_REALM, &self.realm, REALM_MAX_LEN)
+    }
+}
+
+impl fmt::Display for Realm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.realm)
+    }
+}
@@ -0,0 +1,144 @@
+use crate::attrs::attribute::{Attribute, TypedAttr};
+use crate::attrs::RawAttr;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use crate::header::Header;
+use alloc::vec::Vec;
+use core::fmt;
+
+// 应用自定义的 attribute, 例如 0xC000 以上的私有 attribute
+// 解码: TypedAttr, 编码: Into<RawAttr>, 检查: AttrValidator, 输出: Display
+//
+// 注册之后 Packet::validate_with 会和内置的 attribute 一起检查,
+// Attribute::decode_with / Packet::attributes_with 解析成 Attribute::Custom,
+// Packet::display_with 使用 NAME 和 Display 输出
+pub trait CustomAttr: TypedAttr + AttrValidator + Into<RawAttr> + fmt::Display {
+    const NAME: &'static str;
+}
+
+type DecodeFn = fn(&RawAttr, &Header) -> Result<(), ParsePacketErr>;
+type ValidateFn = fn(&RawAttr, &Header) -> Option<ValidateErr>;
+type FmtFn = fn(&RawAttr, &Header, &mut fmt::Formatter<'_>) -> fmt::Result;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    is_type: fn(u16) -> bool,
+    name: &'static str,
+    decode: DecodeFn,
+    validate: ValidateFn,
+    fmt: FmtFn,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AttrRegistry {
+    entries: Vec<Entry>,
+}
+
+impl AttrRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 后注册的优先, 可以覆盖内置 attribute 的解析和检查
+    pub fn register<T: CustomAttr>(&mut self) -> &mut Self {
+        self.entries.push(Entry {
+            is_type: T::is_type,
+            name: T::NAME,
+            decode: decode_typed::<T>,
+            validate: validate_typed::<T>,
+            fmt: fmt_typed::<T>,
+        });
+        self
+    }
+
+    pub fn contains(&self, attr_type: u16) -> bool {
+        self.find(attr_type).is_some()
+    }
+
+    pub fn name(&self, attr_type: u16) -> Option<&'static str> {
+        self.find(attr_type).map(|entry| entry.name)
+    }
+
+    // 没有注册时返回 None, 由调用者使用内置的解析
+    pub fn decode(
+        &self,
+        raw: &RawAttr,
+        header: &Header,
+    ) -> Option<Result<Attribute, ParsePacketErr>> {
+        self.find(raw.attr_type).map(|entry| {
+            (entry.decode)(raw, header)?;
+            Ok(Attribute::Custom(CustomAttribute {
+                raw: raw.clone(),
+                header: header.clone(),
+                entry: *entry,
+            }))
+        })
+    }
+
+    // 没有注册时返回 None, 由调用者使用内置的检查
+    pub fn validate(&self, raw: &RawAttr, header: &Header) -> Option<Option<ValidateErr>> {
+        self.find(raw.attr_type)
+            .map(|entry| (entry.validate)(raw, header))
+    }
+
+    fn find(&self, attr_type: u16) -> Option<&Entry> {
+        self.entries.iter().rev().find(|x| (x.is_type)(attr_type))
+    }
+}
+
+// registry 解析出来的 attribute, 保留原始数据和 header
+// 需要具体类型时使用 CustomAttribute::get
+#[derive(Debug, Clone)]
+pub struct CustomAttribute {
+    pub raw: RawAttr,
+    header: Header,
+    entry: Entry,
+}
+
+impl CustomAttribute {
+    pub fn name(&self) -> &'static str {
+        self.entry.name
+    }
+
+    // 类型不一致时返回 Ok(None)
+    pub fn get<T: CustomAttr>(&self) -> Result<Option<T>, ParsePacketErr> {
+        if !T::is_type(self.raw.attr_type) {
+            return Ok(None);
+        }
+        T::decode(&self.raw, &self.header).map(Some)
+    }
+}
+
+impl AttrValidator for CustomAttribute {
+    fn validate(&self) -> Option<ValidateErr> {
+        (self.entry.validate)(&self.raw, &self.header)
+    }
+}
+
+// 只输出 value, 和内置 attribute 的 Display 一样
+impl fmt::Display for CustomAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (self.entry.fmt)(&self.raw, &self.header, f)
+    }
+}
+
+fn decode_typed<T: CustomAttr>(raw: &RawAttr, header: &Header) -> Result<(), ParsePacketErr> {
+    T::decode(raw, header).map(|_| ())
+}
+
+fn validate_typed<T: CustomAttr>(raw: &RawAttr, header: &Header) -> Option<ValidateErr> {
+    match T::decode(raw, header) {
+        Ok(v) => v.validate(),
+        Err(e) => Some(e.into()),
+    }
+}
+
+fn fmt_typed<T: CustomAttr>(
+    raw: &RawAttr,
+    header: &Header,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    match T::decode(raw, header) {
+        Ok(v) => write!(f, "{}", v),
+        Err(e) => write!(f, "(error: {})", e),
+    }
+}
@@ -1,10 +1,11 @@
-use crate::attrs::RawAttr;
+use crate::attrs::{RawAttr, RawAttrRef};
 use crate::constants::*;
 use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
-use bytes::{BufMut, BytesMut};
-use std::ops::Deref;
+use bytes::BufMut;
+use core::fmt;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ResponsePort {
     pub port: u16,
 }
@@ -15,14 +16,20 @@ impl ResponsePort {
     }
 }
 
+impl ResponsePort {
+    pub fn value_len(&self) -> usize {
+        4
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_u16(self.port);
+        buf.put_u16(0);
+    }
+}
+
 impl From<ResponsePort> for RawAttr {
     fn from(attr: ResponsePort) -> Self {
-        let mut bytes_buf = BytesMut::with_capacity(4);
-        bytes_buf.put_u16(attr.port);
-        bytes_buf.put_u16(0);
-
-        let value = bytes_buf.freeze();
-        RawAttr::new(ATTR_RESPONSE_PORT, value)
+        RawAttr::from_typed(ATTR_RESPONSE_PORT, &attr)
     }
 }
 
@@ -30,14 +37,23 @@ impl TryFrom<RawAttr> for ResponsePort {
     type Error = ParsePacketErr;
 
     fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        RawAttrRef::from(&base_attr).try_into()
+    }
+}
+
+impl TryFrom<RawAttrRef<'_>> for ResponsePort {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttrRef<'_>) -> Result<Self, Self::Error> {
         if base_attr.value.len() != 4 {
-            return Err(ParsePacketErr::BufSize(format!(
-                "response_port attr buf len:{} != 4",
-                base_attr.value.len()
-            )));
+            return Err(ParsePacketErr::AttrLen {
+                attr_type: base_attr.attr_type,
+                expected: 4,
+                actual: base_attr.value.len(),
+            });
         }
 
-        let value = base_attr.value.deref();
+        let value = base_attr.value;
         let port = u16::from_be_bytes([value[0], value[1]]);
 
         Ok(Self { port })
@@ -53,7 +69,15 @@ impl AttrValidator for ResponsePort {
             return None;
         }
 
-        let err_msg = format!("wrong port: {}", self.port);
-        Some(ValidateErr(err_msg))
+        Some(ValidateErr::BadPort {
+            attr_type: ATTR_RESPONSE_PORT,
+            port,
+        })
+    }
+}
+
+impl fmt::Display for ResponsePort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.port)
     }
 }
@@ -0,0 +1,60 @@
+use crate::attrs::{self, RawAttr};
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use alloc::string::{String, ToString};
+use bytes::{BufMut, Bytes};
+use core::fmt;
+
+// rfc 5389, 15.10
+// 软件名称和版本, utf-8, < 128 characters (763 bytes)
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Software {
+    pub software: String,
+}
+
+impl Software {
+    pub fn new(software: &str) -> Self {
+        Self {
+            software: software.to_string(),
+        }
+    }
+}
+
+impl Software {
+    pub fn value_len(&self) -> usize {
+        self.software.len()
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_slice(self.software.as_bytes());
+    }
+}
+
+impl From<Software> for RawAttr {
+    fn from(attr: Software) -> Self {
+        RawAttr::new(ATTR_SOFTWARE, Bytes::from(attr.software.into_bytes()))
+    }
+}
+
+impl TryFrom<RawAttr> for Software {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        let software = attrs::decode_text(&base_attr)?;
+        Ok(Self { software })
+    }
+}
+
+impl AttrValidator for Software {
+    fn validate(&self) -> Option<ValidateErr> {
+        attrs::validate_text(ATTR_SOFTWARE, &self.software, SOFTWARE_MAX_LEN)
+    }
+}
+
+impl fmt::Display for Software {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.software)
+    }
+}
@@ -0,0 +1,80 @@
+use crate::attrs::RawAttr;
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use alloc::vec::Vec;
+use bytes::BufMut;
+use core::fmt;
+use core::ops::Deref;
+
+// rfc 5389, 15.9
+// 420 响应中, 列出不认识的 comprehension-required attribute, 每个 16 bit
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnknownAttributes {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex_u16_seq"))]
+    pub attr_types: Vec<u16>,
+}
+
+impl UnknownAttributes {
+    pub fn new(attr_types: Vec<u16>) -> Self {
+        Self { attr_types }
+    }
+}
+
+impl UnknownAttributes {
+    pub fn value_len(&self) -> usize {
+        self.attr_types.len() * 2
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        for v in self.attr_types.iter() {
+            buf.put_u16(*v);
+        }
+    }
+}
+
+impl From<UnknownAttributes> for RawAttr {
+    fn from(attr: UnknownAttributes) -> Self {
+        RawAttr::from_typed(ATTR_UNKNOWN_ATTRIBUTES, &attr)
+    }
+}
+
+impl TryFrom<RawAttr> for UnknownAttributes {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        let value = base_attr.value.deref();
+        if !value.len().is_multiple_of(2) {
+            return Err(ParsePacketErr::BadAttrValue {
+                attr_type: base_attr.attr_type,
+                reason: "odd value len",
+            });
+        }
+
+        let attr_types = value
+            .chunks(2)
+            .map(|x| u16::from_be_bytes([x[0], x[1]]))
+            .collect();
+
+        Ok(Self { attr_types })
+    }
+}
+
+impl AttrValidator for UnknownAttributes {
+    fn validate(&self) -> Option<ValidateErr> {
+        None
+    }
+}
+
+impl fmt::Display for UnknownAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, v) in self.attr_types.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:#06x}", v)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use crate::attrs::{RawAttr, RawAttrRef};
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use crate::util;
+use bytes::{BufMut, Bytes};
+use core::fmt;
+
+// rfc 8489, 14.4
+// SHA256(username ":" realm), 32 bytes
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Userhash {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex"))]
+    pub hash: [u8; USERHASH_LEN],
+}
+
+impl Userhash {
+    pub fn new(hash: [u8; USERHASH_LEN]) -> Self {
+        Self { hash }
+    }
+}
+
+impl Userhash {
+    pub fn value_len(&self) -> usize {
+        USERHASH_LEN
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_slice(&self.hash);
+    }
+}
+
+impl From<Userhash> for RawAttr {
+    fn from(attr: Userhash) -> Self {
+        RawAttr::new(ATTR_USERHASH, Bytes::copy_from_slice(&attr.hash))
+    }
+}
+
+impl TryFrom<RawAttr> for Userhash {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        RawAttrRef::from(&base_attr).try_into()
+    }
+}
+
+impl TryFrom<RawAttrRef<'_>> for Userhash {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttrRef<'_>) -> Result<Self, Self::Error> {
+        if base_attr.value.len() != USERHASH_LEN {
+            return Err(ParsePacketErr::AttrLen {
+                attr_type: base_attr.attr_type,
+                expected: USERHASH_LEN,
+                actual: base_attr.value.len(),
+            });
+        }
+
+        let mut hash = [0_u8; USERHASH_LEN];
+        hash.copy_from_slice(base_attr.value);
+
+        Ok(Self { hash })
+    }
+}
+
+impl AttrValidator for Userhash {
+    fn validate(&self) -> Option<ValidateErr> {
+        None
+    }
+}
+
+impl fmt::Display for Userhash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", util::Hex(&self.hash))
+    }
+}
@@ -0,0 +1,76 @@
+use crate::attrs::RawAttr;
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use alloc::string::{String, ToString};
+use bytes::{BufMut, Bytes};
+use core::fmt;
+
+// rfc 5389, 15.3
+// utf-8, < 513 bytes
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Username {
+    pub username: String,
+}
+
+impl Username {
+    pub fn new(username: &str) -> Self {
+        Self {
+            username: username.to_string(),
+        }
+    }
+}
+
+impl Username {
+    pub fn value_len(&self) -> usize {
+        self.username.len()
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_slice(self.username.as_bytes());
+    }
+}
+
+impl From<Username> for RawAttr {
+    fn from(attr: Username) -> Self {
+        RawAttr::new(ATTR_USERNAME, Bytes::from(attr.username.into_bytes()))
+    }
+}
+
+impl TryFrom<RawAttr> for Username {
+    type Error = ParsePacketErr;
+
+    fn try_from(base_attr: RawAttr) -> Result<Self, Self::Error> {
+        let username = match String::from_utf8(base_attr.value.to_vec()) {
+            Ok(v) => v,
+            Err(_e) => {
+                return Err(ParsePacketErr::NotUtf8 {
+                    attr_type: base_attr.attr_type,
+                });
+            }
+        };
+
+        Ok(Self { username })
+    }
+}
+
+impl AttrValidator for Username {
+    fn validate(&self) -> Option<ValidateErr> {
+        if self.username.len() <= USERNAME_MAX_LEN {
+            return None;
+        }
+
+        Some(ValidateErr::TooLong {
+            attr_type: ATTR_USERNAME,
+            len: self.username.len(),
+            max: USERNAME_MAX_LEN,
+        })
+    }
+}
+
+impl fmt::Display for Username {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.username)
+    }
+}
@@ -1,77 +1,97 @@
-use crate::attrs::RawAttr;
+use crate::attrs::{RawAttr, RawAttrRef};
 use crate::constants::*;
-use bytes::{BufMut, BytesMut};
-use std::net::SocketAddr;
+use bytes::BufMut;
+use core::net::SocketAddr;
 
 use crate::attrs::address_attr::AddressAttr;
-use crate::error::{AttrValidator, ValidateErr};
-use crate::header::TransId;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use crate::header::{Header, TransId};
 use crate::util;
+use core::fmt;
 
 // xor-mapped-address 端口和ip需要混淆
 // port 和 magic cookie 做 xor
 // address(ipv4) 和 magic cookie做xor
 // address(ipv6) 和 magic cookie + trans_id 做xor
+//
+// rfc 3489 的包没有 magic cookie, 也没有 xor-mapped-address, 解析时返回 NoMagicCookie
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct XorMappedAddress {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::display"))]
     pub address: SocketAddr,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex"))]
+    pub magic_cookie: [u8; MAGIC_COOKIE_LEN],
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex"))]
     pub trans_id: TransId,
 }
 
 impl XorMappedAddress {
     pub fn new(trans_id: TransId, address: SocketAddr) -> Self {
-        Self { trans_id, address }
+        Self {
+            address,
+            magic_cookie: MAGIC_COOKIE,
+            trans_id,
+        }
+    }
+
+    // 总是使用 rfc 5389 的 magic cookie
+    pub fn from_header(header: &Header, address: SocketAddr) -> Self {
+        Self::new(header.trans_id, address)
+    }
+
+    pub fn from_base_attr(base_attr: RawAttr, header: &Header) -> Result<Self, ParsePacketErr> {
+        Self::from_attr_ref(RawAttrRef::from(&base_attr), header)
     }
 
-    pub fn from_base_attr(base_attr: RawAttr, trans_id: &TransId) -> Result<Self, String> {
-        let address_attr: AddressAttr = match base_attr.try_into() {
-            Ok(v) => v,
-            Err(e) => {
-                return Err(format!("{:?}", e));
-            }
-        };
+    pub fn from_attr_ref(
+        base_attr: RawAttrRef<'_>,
+        header: &Header,
+    ) -> Result<Self, ParsePacketErr> {
+        if base_attr.attr_type != ATTR_XOR_MAPPED_ADDRESS {
+            return Err(ParsePacketErr::WrongAttrType {
+                expected: ATTR_XOR_MAPPED_ADDRESS,
+                actual: base_attr.attr_type,
+            });
+        }
 
-        if address_attr.attr_type != ATTR_XOR_MAPPED_ADDRESS {
-            return Err(format!("wrong attr type: {}", address_attr.attr_type));
+        if header.is_classic() {
+            return Err(ParsePacketErr::NoMagicCookie);
         }
 
-        let address = match address_attr.address {
-            SocketAddr::V4(v) => SocketAddr::V4(util::xor_address_v4(v)),
-            SocketAddr::V6(v) => SocketAddr::V6(util::xor_address_v6(v, trans_id)),
-        };
+        let address_attr: AddressAttr = base_attr.try_into()?;
 
-        Ok(Self {
-            address,
-            trans_id: *trans_id,
-        })
+        let address =
+            util::xor_address(address_attr.address, &header.magic_cookie, &header.trans_id);
+
+        Ok(Self::from_header(header, address))
+    }
+}
+
+impl XorMappedAddress {
+    // 混淆之后的地址, 编码和 mapped-address 一样
+    pub fn to_address_attr(&self) -> AddressAttr {
+        let address = util::xor_address(self.address, &self.magic_cookie, &self.trans_id);
+        AddressAttr::new(ATTR_XOR_MAPPED_ADDRESS, address)
+    }
+
+    pub fn encode<B: BufMut>(&self, buf: &mut B) -> usize {
+        self.to_address_attr().encode(buf)
+    }
+
+    pub fn value_len(&self) -> usize {
+        self.to_address_attr().value_len()
+    }
+
+    pub fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        self.to_address_attr().encode_value(buf)
     }
 }
 
 impl From<XorMappedAddress> for RawAttr {
     fn from(attr: XorMappedAddress) -> Self {
-        let xor_socket_addr = util::xor_address(attr.address, &attr.trans_id);
-
-        let (family, port, ip_bytes, ip_len) = match &xor_socket_addr {
-            SocketAddr::V4(addr) => {
-                let ip_bytes: Vec<u8> = addr.ip().octets().into();
-                (ATTR_FAMILY_IPV4, addr.port(), ip_bytes, 4)
-            }
-            SocketAddr::V6(addr) => {
-                let ip_bytes: Vec<u8> = addr.ip().octets().into();
-                (ATTR_FAMILY_IPV6, addr.port(), ip_bytes, 16)
-            }
-        };
-
-        let mut bytes_buf = BytesMut::with_capacity(4 + ip_len);
-
-        bytes_buf.put_u8(0);
-        bytes_buf.put_u8(family);
-        bytes_buf.put_u16(port);
-        bytes_buf.put_slice(&ip_bytes);
-        let value = bytes_buf.freeze();
-
-        RawAttr::new(ATTR_XOR_MAPPED_ADDRESS, value)
+        RawAttr::from_typed(ATTR_XOR_MAPPED_ADDRESS, &attr)
     }
 }
 
@@ -84,7 +104,16 @@ impl AttrValidator for XorMappedAddress {
             return None;
         }
 
-        let err_msg = format!("wrong port: {}", port);
-        Some(ValidateErr(err_msg))
+        Some(ValidateErr::BadPort {
+            attr_type: ATTR_XOR_MAPPED_ADDRESS,
+            port,
+        })
+    }
+}
+
+impl fmt::Display for XorMappedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 已经还原过的地址
+        write!(f, "{}", self.address)
     }
 }
@@ -0,0 +1,138 @@
+use crate::constants::*;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use hmac::{Hmac, Mac};
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use sha2::Sha256;
+
+type HmacSha1 = Hmac<Sha1>;
+type HmacSha256 = Hmac<Sha256>;
+
+// rfc 5389, 15.4
+// short-term credential: key = SASLprep(password)
+// 这里不做 SASLprep, 调用方需要传入处理过的 password
+
+pub fn short_term_key(password: &str) -> Vec<u8> {
+    password.as_bytes().to_vec()
+}
+
+// rfc 5389, 15.4
+// long-term credential: key = MD5(username ":" realm ":" SASLprep(password))
+pub fn long_term_key(username: &str, realm: &str, password: &str) -> Vec<u8> {
+    let mut hasher = Md5::new();
+    hasher.update(username.as_bytes());
+    hasher.update(b":");
+    hasher.update(realm.as_bytes());
+    hasher.update(b":");
+    hasher.update(password.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+// rfc 8489, 9.2.2
+// password-algorithm 是 sha256 时: key = SHA256(username ":" realm ":" OpaqueString(password))
+pub fn long_term_key_sha256(username: &str, realm: &str, password: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(username.as_bytes());
+    hasher.update(b":");
+    hasher.update(realm.as_bytes());
+    hasher.update(b":");
+    hasher.update(password.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+// 不支持的 algorithm 返回 None
+pub fn long_term_key_with(
+    algorithm: u16,
+    username: &str,
+    realm: &str,
+    password: &str,
+) -> Option<Vec<u8>> {
+    match algorithm {
+        PASSWORD_ALGORITHM_MD5 => Some(long_term_key(username, realm, password)),
+        PASSWORD_ALGORITHM_SHA256 => Some(long_term_key_sha256(username, realm, password)),
+        _ => None,
+    }
+}
+
+// rfc 8489, 14.4
+// userhash = SHA256(OpaqueString(username) ":" realm)
+pub fn userhash(username: &str, realm: &str) -> [u8; USERHASH_LEN] {
+    let mut hasher = Sha256::new();
+    hasher.update(username.as_bytes());
+    hasher.update(b":");
+    hasher.update(realm.as_bytes());
+
+    let mut result = [0_u8; USERHASH_LEN];
+    result.copy_from_slice(&hasher.finalize());
+    result
+}
+
+// rfc 8489, 9.2
+// "obMatJos2" + base64(24 bit security feature set)
+pub fn nonce_cookie(features: u32) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut cookie = NONCE_COOKIE.to_string();
+    for i in (0..4).rev() {
+        let index = (features >> (i * 6)) & 0x3f;
+        cookie.push(TABLE[index as usize] as char);
+    }
+    cookie
+}
+
+// nonce 没有以 nonce cookie 开头时返回 None
+pub fn parse_nonce_cookie(nonce: &str) -> Option<u32> {
+    if nonce.len() < NONCE_COOKIE_LEN || !nonce.starts_with(NONCE_COOKIE) {
+        return None;
+    }
+
+    let mut features = 0_u32;
+    // 按字节取, nonce 可能不是 ascii
+    for &c in nonce.as_bytes()[NONCE_COOKIE.len()..NONCE_COOKIE_LEN].iter() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        features = (features << 6) | v as u32;
+    }
+
+    Some(features)
+}
+
+pub fn hmac_sha1(key: &[u8], data: &[u8]) -> [u8; MESSAGE_INTEGRITY_LEN] {
+    // hmac 可以接受任意长度的 key
+    let mut mac = HmacSha1::new_from_slice(key).expect("hmac key");
+    mac.update(data);
+
+    let mut result = [0_u8; MESSAGE_INTEGRITY_LEN];
+    result.copy_from_slice(&mac.finalize().into_bytes());
+    result
+}
+
+// 常量时间比较
+pub fn verify_hmac_sha1(key: &[u8], data: &[u8], expected: &[u8]) -> bool {
+    let mut mac = HmacSha1::new_from_slice(key).expect("hmac key");
+    mac.update(data);
+    mac.verify_slice(expected).is_ok()
+}
+
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; MESSAGE_INTEGRITY_SHA256_LEN] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("hmac key");
+    mac.update(data);
+
+    let mut result = [0_u8; MESSAGE_INTEGRITY_SHA256_LEN];
+    result.copy_from_slice(&mac.finalize().into_bytes());
+    result
+}
+
+// expected 可能是截断的 (16 - 32 bytes)
+pub fn verify_hmac_sha256(key: &[u8], data: &[u8], expected: &[u8]) -> bool {
+    let mut mac = HmacSha256::new_from_slice(key).expect("hmac key");
+    mac.update(data);
+    mac.verify_truncated_left(expected).is_ok()
+}
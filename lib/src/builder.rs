@@ -0,0 +1,336 @@
+use crate::attrs::address_attr::AddressAttr;
+use crate::attrs::attribute::Attribute;
+use crate::attrs::change_request::ChangeRequest;
+use crate::attrs::errcode_attr::ErrcodeAttr;
+use crate::attrs::fingerprint::Fingerprint;
+use crate::attrs::nonce::Nonce;
+use crate::attrs::padding_attr::PaddingAttr;
+use crate::attrs::password_algorithm::{PasswordAlgorithm, PasswordAlgorithms};
+use crate::attrs::realm::Realm;
+use crate::attrs::registry::CustomAttr;
+use crate::attrs::response_port::ResponsePort;
+use crate::attrs::software::Software;
+use crate::attrs::unknown_attributes::UnknownAttributes;
+use crate::attrs::userhash::Userhash;
+use crate::attrs::username::Username;
+use crate::attrs::xor_address::XorMappedAddress;
+use crate::attrs::{AttrPadding, RawAttr};
+use crate::auth;
+use crate::constants::*;
+use crate::error::ParsePacketErr;
+use crate::header::{ClassicTransId, Header, TransId};
+use crate::message_type::{MessageClass, MessageType};
+use crate::packet::{to_msg_len, Packet};
+use crate::util::new_trans_id_with;
+use alloc::vec;
+use alloc::vec::Vec;
+use bytes::{BufMut, Bytes, BytesMut};
+use rand::RngCore;
+
+// 组装 stun 包
+//
+// let buf = MessageBuilder::request(METHOD_BINDING)?
+//     .attr(ChangeRequest::new(true, false))
+//     .message_integrity(&key)
+//     .fingerprint()
+//     .build()?;
+//
+// attribute 按添加的顺序写入, 最后依次是
+// message-integrity, message-integrity-sha256, fingerprint (rfc 5389, 15.4 15.5, rfc 8489, 14.6)
+// attribute 在 build 时直接编码到输出的 buf
+
+#[derive(Debug, Clone)]
+pub struct MessageBuilder {
+    header: Header,
+    attrs: Vec<Attribute>,
+    integrity_key: Option<Vec<u8>>,
+    integrity_sha256_key: Option<Vec<u8>>,
+    fingerprint: bool,
+    padding: AttrPadding,
+}
+
+impl MessageBuilder {
+    fn with_header(header: Header) -> Self {
+        Self {
+            header,
+            attrs: vec![],
+            integrity_key: None,
+            integrity_sha256_key: None,
+            fingerprint: false,
+            padding: AttrPadding::Padded,
+        }
+    }
+
+    // 随机生成 transaction id
+    // request / indication 的 method 超过 12 bit 时返回 BadMethod
+    #[cfg(feature = "std")]
+    pub fn new(msg_type: MessageType) -> Self {
+        Self::new_with_rng(msg_type, &mut rand::thread_rng())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn request(method: u16) -> Result<Self, ParsePacketErr> {
+        Ok(Self::new(MessageType::new(method, MessageClass::Request)?))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn indication(method: u16) -> Result<Self, ParsePacketErr> {
+        Ok(Self::new(MessageType::new(
+            method,
+            MessageClass::Indication,
+        )?))
+    }
+
+    // no_std 时由调用者提供随机数生成器
+    pub fn new_with_rng<R: RngCore + ?Sized>(msg_type: MessageType, rng: &mut R) -> Self {
+        Self::with_header(Header::new(msg_type, 0, new_trans_id_with(rng)))
+    }
+
+    pub fn request_with_rng<R: RngCore + ?Sized>(
+        method: u16,
+        rng: &mut R,
+    ) -> Result<Self, ParsePacketErr> {
+        Ok(Self::new_with_rng(
+            MessageType::new(method, MessageClass::Request)?,
+            rng,
+        ))
+    }
+
+    pub fn indication_with_rng<R: RngCore + ?Sized>(
+        method: u16,
+        rng: &mut R,
+    ) -> Result<Self, ParsePacketErr> {
+        Ok(Self::new_with_rng(
+            MessageType::new(method, MessageClass::Indication)?,
+            rng,
+        ))
+    }
+
+    // 响应包, 使用请求的 magic cookie 和 transaction id
+    pub fn reply(req: &Header, msg_type: MessageType) -> Self {
+        Self::with_header(req.reply(msg_type))
+    }
+
+    pub fn trans_id(mut self, trans_id: TransId) -> Self {
+        self.header.trans_id = trans_id;
+        self
+    }
+
+    // rfc 3489 的请求
+    pub fn classic_trans_id(mut self, trans_id: ClassicTransId) -> Self {
+        self.header = Header::new_classic(self.header.msg_type, 0, trans_id);
+        self
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn attr<T: BuilderAttr>(mut self, attr: T) -> Self {
+        self.attrs.push(attr.into_attribute());
+        self
+    }
+
+    pub fn attr_opt<T: BuilderAttr>(self, attr: Option<T>) -> Self {
+        match attr {
+            Some(v) => self.attr(v),
+            None => self,
+        }
+    }
+
+    pub fn message_integrity(mut self, key: &[u8]) -> Self {
+        self.integrity_key = Some(key.to_vec());
+        self
+    }
+
+    pub fn message_integrity_sha256(mut self, key: &[u8]) -> Self {
+        self.integrity_sha256_key = Some(key.to_vec());
+        self
+    }
+
+    pub fn fingerprint(mut self) -> Self {
+        self.fingerprint = true;
+        self
+    }
+
+    // 对端是不做 padding 的 rfc 3489 实现时, 使用 Unpadded
+    pub fn padding(mut self, padding: AttrPadding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    // 打包之后的长度, 包括 header
+    pub fn encoded_len(&self) -> usize {
+        let mut total = self.attrs_len();
+        if self.integrity_key.is_some() {
+            total += 4 + MESSAGE_INTEGRITY_LEN;
+        }
+        if self.integrity_sha256_key.is_some() {
+            total += 4 + MESSAGE_INTEGRITY_SHA256_LEN;
+        }
+        if self.fingerprint {
+            total += 4 + FINGERPRINT_LEN;
+        }
+
+        HEADER_LEN + total
+    }
+
+    // 一次写入一个 buf, msg_len 超过 u16 时返回 TooLarge
+    pub fn build(self) -> Result<Bytes, ParsePacketErr> {
+        to_msg_len(self.encoded_len() - HEADER_LEN)?;
+
+        let mut buf = BytesMut::zeroed(self.encoded_len());
+        self.write(&mut buf);
+        Ok(buf.freeze())
+    }
+
+    // 写到调用者提供的 buf, 返回写入的长度, buf 不够时返回 BufSize
+    pub fn build_into(&self, buf: &mut [u8]) -> Result<usize, ParsePacketErr> {
+        let total = self.encoded_len();
+        to_msg_len(total - HEADER_LEN)?;
+
+        if buf.len() < total {
+            return Err(ParsePacketErr::BufSize {
+                expected: total,
+                actual: buf.len(),
+            });
+        }
+
+        Ok(self.write(&mut buf[..total]))
+    }
+
+    fn attrs_len(&self) -> usize {
+        self.attrs
+            .iter()
+            .fold(0_usize, |acc, x| acc + x.len_with(self.padding))
+    }
+
+    // out 的长度正好是 encoded_len, msg_len 已经检查过
+    fn write(&self, out: &mut [u8]) -> usize {
+        let attrs_len = self.attrs_len();
+
+        let mut header = self.header.clone();
+        header.msg_len = attrs_len as u16;
+
+        let mut buf = &mut out[..];
+        let mut offset = header.encode(&mut buf);
+        for v in self.attrs.iter() {
+            offset += v.encode(&mut buf, self.padding);
+        }
+
+        // 每次计算之前, msg_len 要包括当前这个 attribute
+        let mut msg_len = attrs_len;
+
+        if let Some(key) = &self.integrity_key {
+            msg_len += 4 + MESSAGE_INTEGRITY_LEN;
+            set_msg_len(out, msg_len);
+
+            let hmac = auth::hmac_sha1(key, &out[..offset]);
+            let mut buf = &mut out[offset..];
+            buf.put_u16(ATTR_MESSAGE_INTEGRITY);
+            buf.put_u16(MESSAGE_INTEGRITY_LEN as u16);
+            buf.put_slice(&hmac);
+            offset += 4 + MESSAGE_INTEGRITY_LEN;
+        }
+
+        if let Some(key) = &self.integrity_sha256_key {
+            msg_len += 4 + MESSAGE_INTEGRITY_SHA256_LEN;
+            set_msg_len(out, msg_len);
+
+            let hmac = auth::hmac_sha256(key, &out[..offset]);
+            let mut buf = &mut out[offset..];
+            buf.put_u16(ATTR_MESSAGE_INTEGRITY_SHA256);
+            buf.put_u16(MESSAGE_INTEGRITY_SHA256_LEN as u16);
+            buf.put_slice(&hmac);
+            offset += 4 + MESSAGE_INTEGRITY_SHA256_LEN;
+        }
+
+        if self.fingerprint {
+            msg_len += 4 + FINGERPRINT_LEN;
+            set_msg_len(out, msg_len);
+
+            let crc = Fingerprint::calculate(&out[..offset]).crc;
+            let mut buf = &mut out[offset..];
+            buf.put_u16(ATTR_FINGERPRINT);
+            buf.put_u16(FINGERPRINT_LEN as u16);
+            buf.put_u32(crc);
+            offset += 4 + FINGERPRINT_LEN;
+        }
+
+        offset
+    }
+
+    // 不打包, 返回 Packet (总是 padding)
+    pub fn build_packet(self) -> Result<Packet, ParsePacketErr> {
+        let attrs = self.attrs.into_iter().map(RawAttr::from).collect();
+        let mut packet = Packet::new(self.header, attrs);
+
+        if let Some(key) = &self.integrity_key {
+            packet.add_message_integrity(key)?;
+        }
+        if let Some(key) = &self.integrity_sha256_key {
+            packet.add_message_integrity_sha256(key)?;
+        }
+        if self.fingerprint {
+            packet.add_fingerprint()?;
+        }
+
+        Ok(packet)
+    }
+}
+
+// 可以通过 MessageBuilder::attr 添加的 attribute
+// message-integrity(-sha256) 和 fingerprint 由 build 计算, 没有实现这个 trait,
+// 使用 message_integrity / message_integrity_sha256 / fingerprint 添加
+pub trait BuilderAttr {
+    fn into_attribute(self) -> Attribute;
+}
+
+macro_rules! impl_builder_attr {
+    ($t:ty, $variant:ident) => {
+        impl BuilderAttr for $t {
+            fn into_attribute(self) -> Attribute {
+                Attribute::$variant(self)
+            }
+        }
+    };
+}
+
+impl_builder_attr!(ChangeRequest, ChangeRequest);
+impl_builder_attr!(Username, Username);
+impl_builder_attr!(ErrcodeAttr, ErrorCode);
+impl_builder_attr!(UnknownAttributes, UnknownAttributes);
+impl_builder_attr!(Realm, Realm);
+impl_builder_attr!(Nonce, Nonce);
+impl_builder_attr!(PasswordAlgorithm, PasswordAlgorithm);
+impl_builder_attr!(Userhash, Userhash);
+impl_builder_attr!(PaddingAttr, Padding);
+impl_builder_attr!(ResponsePort, ResponsePort);
+impl_builder_attr!(PasswordAlgorithms, PasswordAlgorithms);
+impl_builder_attr!(XorMappedAddress, XorMappedAddress);
+impl_builder_attr!(Software, Software);
+
+impl BuilderAttr for AddressAttr {
+    fn into_attribute(self) -> Attribute {
+        match self.attr_type {
+            ATTR_MAPPED_ADDRESS => Attribute::MappedAddress(self),
+            ATTR_SOURCE_ADDRESS => Attribute::SourceAddress(self),
+            ATTR_CHANGED_ADDRESS => Attribute::ChangedAddress(self),
+            ATTR_RESPONSE_ORIGIN => Attribute::ResponseOrigin(self),
+            ATTR_OTHER_ADDRESS => Attribute::OtherAddress(self),
+            ATTR_ALTERNATE_SERVER => Attribute::AlternateServer(self),
+            _ => Attribute::Unknown(self.into()),
+        }
+    }
+}
+
+// AttrRegistry 的自定义 attribute, 按 Into<RawAttr> 编码
+impl<T: CustomAttr> BuilderAttr for T {
+    fn into_attribute(self) -> Attribute {
+        Attribute::Unknown(self.into())
+    }
+}
+
+fn set_msg_len(buf: &mut [u8], msg_len: usize) {
+    buf[2..4].copy_from_slice(&(msg_len as u16).to_be_bytes());
+}
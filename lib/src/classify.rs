@@ -0,0 +1,74 @@
+use crate::attrs::fingerprint::Fingerprint;
+use crate::constants::*;
+
+// rfc 7983, 7
+// 同一个 udp socket 上区分 stun, dtls, rtp/rtcp, turn channel data
+// 只看第一个字节和 header, 不解析 attribute
+//
+//  0 -   3: stun
+// 64 -  79: turn channel data
+// 其它: dtls (20 - 63), rtp/rtcp (128 - 191) 等
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketKind {
+    Stun,
+    ChannelData,
+    Foreign,
+}
+
+// 检查第一个字节 (最高两位是 0), magic cookie, msg_len 是 4 的倍数并且和 buf 长度一致
+pub fn classify(buf: &[u8]) -> PacketKind {
+    match buf.first() {
+        Some(0..=3) if is_stun(buf) => PacketKind::Stun,
+        Some(64..=79) if is_channel_data(buf) => PacketKind::ChannelData,
+        _ => PacketKind::Foreign,
+    }
+}
+
+// 另外要求最后一个 attribute 是正确的 fingerprint (rfc 5389, 8)
+pub fn classify_with_fingerprint(buf: &[u8]) -> PacketKind {
+    match classify(buf) {
+        PacketKind::Stun if has_fingerprint(buf) => PacketKind::Stun,
+        PacketKind::Stun => PacketKind::Foreign,
+        v => v,
+    }
+}
+
+fn is_stun(buf: &[u8]) -> bool {
+    if buf.len() < HEADER_LEN || buf[4..8] != MAGIC_COOKIE {
+        return false;
+    }
+
+    let msg_len = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+    msg_len.is_multiple_of(4) && HEADER_LEN + msg_len == buf.len()
+}
+
+// rfc 8656, 12.4
+// channel number (0x4000 - 0x4fff), length, data
+// udp 上可能带了 padding
+fn is_channel_data(buf: &[u8]) -> bool {
+    if buf.len() < 4 {
+        return false;
+    }
+
+    let len = 4 + u16::from_be_bytes([buf[2], buf[3]]) as usize;
+    len <= buf.len() && buf.len() - len < 4
+}
+
+fn has_fingerprint(buf: &[u8]) -> bool {
+    let attr_len = 4 + FINGERPRINT_LEN;
+    if buf.len() < HEADER_LEN + attr_len {
+        return false;
+    }
+
+    let index = buf.len() - attr_len;
+    let attr = &buf[index..];
+    if u16::from_be_bytes([attr[0], attr[1]]) != ATTR_FINGERPRINT
+        || u16::from_be_bytes([attr[2], attr[3]]) as usize != FINGERPRINT_LEN
+    {
+        return false;
+    }
+
+    let crc = u32::from_be_bytes([attr[4], attr[5], attr[6], attr[7]]);
+    Fingerprint::calculate(&buf[..index]).crc == crc
+}
@@ -1,14 +1,84 @@
+use crate::message_type::{MessageClass, MessageType};
+
 // 0x2112A442
 pub const MAGIC_COOKIE: [u8; 4] = [0x21, 0x12, 0xA4, 0x42];
 
-pub const TRANS_ID_LEN: usize = 16;
+pub const MAGIC_COOKIE_LEN: usize = 4;
+
+pub const TRANS_ID_LEN: usize = 12;
+pub const CLASSIC_TRANS_ID_LEN: usize = 16;
 pub const HEADER_LEN: usize = 20;
 
+// 一个包最多解析的 attribute 个数 (DecodeOptions 的默认值)
+pub const MAX_ATTRS: usize = 32;
+
+// msg_len 是 u16
+pub const MAX_MESSAGE_SIZE: usize = HEADER_LEN + u16::MAX as usize;
+
+// rfc 5389, 15.5
+pub const FINGERPRINT_LEN: usize = 4;
+pub const FINGERPRINT_XOR: u32 = 0x5354_554e;
+
+// rfc 5389, 15
+pub const USERNAME_MAX_LEN: usize = 512;
+pub const REALM_MAX_LEN: usize = 763;
+pub const NONCE_MAX_LEN: usize = 763;
+pub const SOFTWARE_MAX_LEN: usize = 763;
+
+// hmac-sha1
+pub const MESSAGE_INTEGRITY_LEN: usize = 20;
+
+// hmac-sha256, rfc 8489, 14.6
+pub const MESSAGE_INTEGRITY_SHA256_LEN: usize = 32;
+pub const MESSAGE_INTEGRITY_SHA256_MIN_LEN: usize = 16;
+
+// rfc 8489, 14.4
+pub const USERHASH_LEN: usize = 32;
+
+// rfc 8489, 18.5
+pub const PASSWORD_ALGORITHM_MD5: u16 = 0x0001;
+pub const PASSWORD_ALGORITHM_SHA256: u16 = 0x0002;
+
+// rfc 8489, 9.2
+// nonce = "obMatJos2" + base64(security feature set, 24 bit) + ...
+pub const NONCE_COOKIE: &str = "obMatJos2";
+pub const NONCE_COOKIE_LEN: usize = 13;
+
+// rfc 8489, 18.1, bit 0 是最高位
+pub const SECURITY_FEATURE_PASSWORD_ALGORITHMS: u32 = 0x80_0000;
+pub const SECURITY_FEATURE_USERNAME_ANONYMITY: u32 = 0x40_0000;
+
+pub const ERROR_CODE_TRY_ALTERNATE: u16 = 300;
 pub const ERROR_CODE_BAD_REQUEST: u16 = 400;
+pub const ERROR_CODE_UNAUTHORIZED: u16 = 401;
+pub const ERROR_CODE_UNKNOWN_ATTRIBUTE: u16 = 420;
+pub const ERROR_CODE_STALE_NONCE: u16 = 438;
 
-pub const MESSAGE_TYPE_BIND_REQ: u16 = 0x0001;
-pub const MESSAGE_TYPE_BIND_RES: u16 = 0x0101;
-pub const MESSAGE_TYPE_BIND_ERR_RES: u16 = 0x0111;
+// 12 bit
+pub const MESSAGE_METHOD_MASK: u16 = 0x0fff;
+
+pub const METHOD_BINDING: u16 = 0x0001;
+
+// rfc 8656 (turn)
+pub const METHOD_ALLOCATE: u16 = 0x0003;
+pub const METHOD_REFRESH: u16 = 0x0004;
+pub const METHOD_SEND: u16 = 0x0006;
+pub const METHOD_DATA: u16 = 0x0007;
+pub const METHOD_CREATE_PERMISSION: u16 = 0x0008;
+pub const METHOD_CHANNEL_BIND: u16 = 0x0009;
+
+// 0x0001
+pub const MESSAGE_TYPE_BIND_REQ: MessageType =
+    MessageType::new_unchecked(METHOD_BINDING, MessageClass::Request);
+// 0x0011
+pub const MESSAGE_TYPE_BIND_IND: MessageType =
+    MessageType::new_unchecked(METHOD_BINDING, MessageClass::Indication);
+// 0x0101
+pub const MESSAGE_TYPE_BIND_RES: MessageType =
+    MessageType::new_unchecked(METHOD_BINDING, MessageClass::SuccessResponse);
+// 0x0111
+pub const MESSAGE_TYPE_BIND_ERR_RES: MessageType =
+    MessageType::new_unchecked(METHOD_BINDING, MessageClass::ErrorResponse);
 
 pub const ATTR_FAMILY_IPV4: u8 = 0x01;
 pub const ATTR_FAMILY_IPV6: u8 = 0x02;
@@ -17,10 +87,28 @@ pub const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
 pub const ATTR_CHANGE_REQUEST: u16 = 0x0003;
 pub const ATTR_SOURCE_ADDRESS: u16 = 0x0004;
 pub const ATTR_CHANGED_ADDRESS: u16 = 0x0005;
+pub const ATTR_USERNAME: u16 = 0x0006;
+pub const ATTR_MESSAGE_INTEGRITY: u16 = 0x0008;
 pub const ATTR_ERROR_CODE: u16 = 0x0009;
+pub const ATTR_UNKNOWN_ATTRIBUTES: u16 = 0x000a;
+pub const ATTR_REALM: u16 = 0x0014;
+pub const ATTR_NONCE: u16 = 0x0015;
+pub const ATTR_MESSAGE_INTEGRITY_SHA256: u16 = 0x001c;
+pub const ATTR_PASSWORD_ALGORITHM: u16 = 0x001d;
+pub const ATTR_USERHASH: u16 = 0x001e;
+pub const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
 pub const ATTR_PADDING: u16 = 0x0026;
 pub const ATTR_RESPONSE_PORT: u16 = 0x0027;
 
-pub const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x8020;
+// rfc 5389, 15
+// 0x0000-0x7fff: comprehension-required, 0x8000-0xffff: comprehension-optional
+pub const ATTR_COMPREHENSION_OPTIONAL_MIN: u16 = 0x8000;
+
+pub const ATTR_PASSWORD_ALGORITHMS: u16 = 0x8002;
+// rfc 5389 之前的草案使用 0x8020, 有些 rfc 3489 的服务器还在用
+pub const ATTR_XOR_MAPPED_ADDRESS_LEGACY: u16 = 0x8020;
+pub const ATTR_SOFTWARE: u16 = 0x8022;
+pub const ATTR_ALTERNATE_SERVER: u16 = 0x8023;
+pub const ATTR_FINGERPRINT: u16 = 0x8028;
 pub const ATTR_RESPONSE_ORIGIN: u16 = 0x802b;
 pub const ATTR_OTHER_ADDRESS: u16 = 0x802c;
@@ -1,27 +1,336 @@
-#[derive(Debug)]
+use crate::constants::{ERROR_CODE_BAD_REQUEST, ERROR_CODE_UNAUTHORIZED};
+use alloc::string::String;
+use core::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     Parse(ParsePacketErr),
     Validate(ValidateErr),
 }
-#[derive(Debug)]
-pub struct ValidateErr(pub String);
 
-#[derive(Debug)]
+impl Error {
+    // 服务端应该回复的错误码, None 表示直接丢弃
+    pub fn error_code(&self) -> Option<u16> {
+        match self {
+            Error::Parse(e) => e.error_code(),
+            Error::Validate(e) => e.error_code(),
+        }
+    }
+}
+
+impl From<ParsePacketErr> for Error {
+    fn from(e: ParsePacketErr) -> Self {
+        Error::Parse(e)
+    }
+}
+
+impl From<ValidateErr> for Error {
+    fn from(e: ValidateErr) -> Self {
+        Error::Validate(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "parse error: {}", e),
+            Error::Validate(e) => write!(f, "validate error: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Parse(e) => Some(e),
+            Error::Validate(e) => Some(e),
+        }
+    }
+}
+
+// offset 都是相对于 stun 包开头 (包括 header)
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParsePacketErr {
-    // 长度或值不匹配
-    NotMatch(String),
+    // buf 不够, 比如不到一个 header, 或者编码时 buf 太小
+    BufSize {
+        expected: usize,
+        actual: usize,
+    },
+
+    // header 里的 msg_len 和实际的数据长度不一致
+    MsgLen {
+        msg_len: usize,
+        actual: usize,
+    },
+
+    // 超过 DecodeOptions::max_size, 或者编码时 msg_len 超过 u16
+    TooLarge {
+        max: usize,
+        actual: usize,
+    },
 
-    // buf不够
-    BufSize(String),
+    // msg_len 不是 4 的倍数
+    MsgLenNotAligned(usize),
 
-    //字段的值不合规
-    BadValue(String),
+    // 没有 magic cookie (rfc 3489 的包)
+    NoMagicCookie,
+
+    // attribute 的 value (或 padding) 超出了 buf
+    AttrTruncated {
+        attr_type: u16,
+        offset: usize,
+        expected: usize,
+        actual: usize,
+    },
+
+    // 最后剩下不到 4 字节, 不够一个 attribute
+    TrailingBytes {
+        offset: usize,
+        len: usize,
+    },
+
+    // attribute value 的长度不对 (固定长度或者最小长度)
+    AttrLen {
+        attr_type: u16,
+        expected: usize,
+        actual: usize,
+    },
+
+    // 字段的值不合规
+    BadAttrValue {
+        attr_type: u16,
+        reason: &'static str,
+    },
+
+    // address attribute 的 family 不是 ipv4/ipv6
+    BadFamily {
+        attr_type: u16,
+        family: u8,
+    },
+
+    // message type 最高两位不是 0
+    BadMessageType(u16),
+
+    // 构造 MessageType 时 method 超过 12 bit
+    BadMethod(u16),
+
+    // 不是 utf8 字符串
+    NotUtf8 {
+        attr_type: u16,
+    },
+
+    // attribute 过多
+    TooManyAttrs {
+        max: usize,
+    },
+
+    WrongAttrType {
+        expected: u16,
+        actual: u16,
+    },
+
+    FingerprintNotLast {
+        offset: usize,
+    },
+
+    FingerprintMismatch {
+        expected: u32,
+        actual: u32,
+    },
+}
 
-    // 不是utf8字符串
-    NotUtf8,
+impl ParsePacketErr {
+    // rfc 5389, 7.3
+    // 长度不对, magic cookie, fingerprint 不对时不是 stun 包, 直接丢弃
+    // 其他的是格式错误的 stun 请求, 回复 400
+    pub fn error_code(&self) -> Option<u16> {
+        match self {
+            ParsePacketErr::BufSize { .. }
+            | ParsePacketErr::MsgLen { .. }
+            | ParsePacketErr::TooLarge { .. }
+            | ParsePacketErr::MsgLenNotAligned(_)
+            | ParsePacketErr::NoMagicCookie
+            | ParsePacketErr::BadMessageType(_)
+            | ParsePacketErr::FingerprintNotLast { .. }
+            | ParsePacketErr::FingerprintMismatch { .. } => None,
+            _ => Some(ERROR_CODE_BAD_REQUEST),
+        }
+    }
+
+    // 出错的 attribute type
+    pub fn attr_type(&self) -> Option<u16> {
+        match self {
+            ParsePacketErr::AttrTruncated { attr_type, .. }
+            | ParsePacketErr::AttrLen { attr_type, .. }
+            | ParsePacketErr::BadAttrValue { attr_type, .. }
+            | ParsePacketErr::BadFamily { attr_type, .. }
+            | ParsePacketErr::NotUtf8 { attr_type } => Some(*attr_type),
+            ParsePacketErr::WrongAttrType { actual, .. } => Some(*actual),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ParsePacketErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsePacketErr::BufSize { expected, actual } => {
+                write!(f, "buf len {} < {}", actual, expected)
+            }
+            ParsePacketErr::MsgLen { msg_len, actual } => {
+                write!(f, "header msg len {} != data len {}", msg_len, actual)
+            }
+            ParsePacketErr::TooLarge { max, actual } => {
+                write!(f, "message size {} > {}", actual, max)
+            }
+            ParsePacketErr::MsgLenNotAligned(v) => {
+                write!(f, "msg len {} is not a multiple of 4", v)
+            }
+            ParsePacketErr::NoMagicCookie => write!(f, "no magic cookie"),
+            ParsePacketErr::AttrTruncated {
+                attr_type,
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "attr {:#06x} at offset {} truncated: need {} bytes, {} left",
+                attr_type, offset, expected, actual
+            ),
+            ParsePacketErr::TrailingBytes { offset, len } => {
+                write!(f, "{} trailing bytes at offset {}", len, offset)
+            }
+            ParsePacketErr::AttrLen {
+                attr_type,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "attr {:#06x} value len {}, expected {}",
+                attr_type, actual, expected
+            ),
+            ParsePacketErr::BadAttrValue { attr_type, reason } => {
+                write!(f, "attr {:#06x} bad value: {}", attr_type, reason)
+            }
+            ParsePacketErr::BadFamily { attr_type, family } => {
+                write!(f, "attr {:#06x} bad ip family: {}", attr_type, family)
+            }
+            ParsePacketErr::BadMessageType(v) => {
+                write!(f, "message type {:#06x}, top two bits not zero", v)
+            }
+            ParsePacketErr::BadMethod(v) => write!(f, "message method {:#x} is over 12 bits", v),
+            ParsePacketErr::NotUtf8 { attr_type } => {
+                write!(f, "attr {:#06x} is not utf8", attr_type)
+            }
+            ParsePacketErr::TooManyAttrs { max } => write!(f, "more than {} attrs", max),
+            ParsePacketErr::WrongAttrType { expected, actual } => {
+                write!(f, "attr type {:#06x}, expected {:#06x}", actual, expected)
+            }
+            ParsePacketErr::FingerprintNotLast { offset } => {
+                write!(f, "fingerprint at offset {} is not the last attr", offset)
+            }
+            ParsePacketErr::FingerprintMismatch { expected, actual } => {
+                write!(f, "fingerprint {:#010x} != {:#010x}", actual, expected)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParsePacketErr {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidateErr {
+    // validate 时 attribute 解析失败
+    Parse(ParsePacketErr),
+
+    TooLong {
+        attr_type: u16,
+        len: usize,
+        max: usize,
+    },
+
+    BadPort {
+        attr_type: u16,
+        port: u16,
+    },
+
+    BadErrorCode(u16),
+
+    BadPasswordAlgorithm(u16),
+
+    EmptyPasswordAlgorithms,
+
+    // 不是这个结构体能表示的 attr type
+    WrongAttrType(u16),
+
+    MissingAttr(u16),
+
+    // message-integrity(-sha256) 不一致, attr type 区分是哪一个
+    IntegrityMismatch(u16),
+
+    FingerprintNotLast,
+
+    FingerprintMismatch,
+
+    // 自定义 attribute 或者应用层的检查
+    Other(String),
+}
+
+impl ValidateErr {
+    // 服务端应该回复的错误码, None 表示直接丢弃
+    pub fn error_code(&self) -> Option<u16> {
+        match self {
+            ValidateErr::Parse(e) => e.error_code(),
+            ValidateErr::FingerprintNotLast | ValidateErr::FingerprintMismatch => None,
+            ValidateErr::IntegrityMismatch(_) => Some(ERROR_CODE_UNAUTHORIZED),
+            _ => Some(ERROR_CODE_BAD_REQUEST),
+        }
+    }
+}
+
+impl From<ParsePacketErr> for ValidateErr {
+    fn from(e: ParsePacketErr) -> Self {
+        ValidateErr::Parse(e)
+    }
+}
+
+impl fmt::Display for ValidateErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidateErr::Parse(e) => write!(f, "{}", e),
+            ValidateErr::TooLong {
+                attr_type,
+                len,
+                max,
+            } => write!(f, "attr {:#06x} too long: {} > {}", attr_type, len, max),
+            ValidateErr::BadPort { attr_type, port } => {
+                write!(f, "attr {:#06x} wrong port: {}", attr_type, port)
+            }
+            ValidateErr::BadErrorCode(v) => write!(f, "wrong error code: {}", v),
+            ValidateErr::BadPasswordAlgorithm(v) => {
+                write!(f, "not support password algorithm: {}", v)
+            }
+            ValidateErr::EmptyPasswordAlgorithms => write!(f, "empty password algorithms"),
+            ValidateErr::WrongAttrType(v) => write!(f, "wrong attr type: {:#06x}", v),
+            ValidateErr::MissingAttr(v) => write!(f, "no attr {:#06x}", v),
+            ValidateErr::IntegrityMismatch(v) => write!(f, "attr {:#06x} not match", v),
+            ValidateErr::FingerprintNotLast => write!(f, "fingerprint is not the last attr"),
+            ValidateErr::FingerprintMismatch => write!(f, "fingerprint not match"),
+            ValidateErr::Other(v) => write!(f, "{}", v),
+        }
+    }
+}
 
-    // attribute过多
-    TooManyAttrs,
+#[cfg(feature = "std")]
+impl std::error::Error for ValidateErr {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ValidateErr::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 pub trait AttrValidator {
@@ -3,83 +3,159 @@
 use crate::constants::*;
 use bytes::{BufMut, Bytes, BytesMut};
 
-use crate::error::{ParsePacketErr, ValidateErr};
-use std::ops::Deref;
+use crate::error::ParsePacketErr;
+use crate::message_type::MessageType;
+use crate::util;
+use core::fmt;
+use core::ops::Deref;
 
+// rfc 5389, 96 bit
 pub type TransId = [u8; TRANS_ID_LEN];
 
+// rfc 3489, 128 bit
+pub type ClassicTransId = [u8; CLASSIC_TRANS_ID_LEN];
+
 // rfc 3489, 11.1
+// rfc 5389, 6
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Header {
-    pub msg_type: u16,
+    pub msg_type: MessageType,
 
     // 不包括header的20字节
     pub msg_len: u16,
 
+    // rfc 3489 的请求中, 这4个字节是 transaction id 的一部分
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex"))]
+    pub magic_cookie: [u8; MAGIC_COOKIE_LEN],
+
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::hex"))]
     pub trans_id: TransId,
 }
 
 impl Header {
-    pub fn new(msg_type: u16, msg_len: u16, trans_id: TransId) -> Self {
+    pub fn new(msg_type: MessageType, msg_len: u16, trans_id: TransId) -> Self {
         Self {
             msg_type,
             msg_len,
+            magic_cookie: MAGIC_COOKIE,
             trans_id,
         }
     }
 
+    pub fn new_classic(msg_type: MessageType, msg_len: u16, trans_id: ClassicTransId) -> Self {
+        let mut magic_cookie = [0_u8; MAGIC_COOKIE_LEN];
+        magic_cookie.copy_from_slice(&trans_id[..MAGIC_COOKIE_LEN]);
+
+        let mut id = [0_u8; TRANS_ID_LEN];
+        id.copy_from_slice(&trans_id[MAGIC_COOKIE_LEN..]);
+
+        Self {
+            msg_type,
+            msg_len,
+            magic_cookie,
+            trans_id: id,
+        }
+    }
+
+    // 没有 magic cookie, 是 rfc 3489 的包
+    pub fn is_classic(&self) -> bool {
+        self.magic_cookie != MAGIC_COOKIE
+    }
+
+    pub fn is_modern(&self) -> bool {
+        !self.is_classic()
+    }
+
+    // 完整的 128 bit, rfc 3489 的 transaction id
+    pub fn classic_trans_id(&self) -> ClassicTransId {
+        let mut trans_id = [0_u8; CLASSIC_TRANS_ID_LEN];
+        trans_id[..MAGIC_COOKIE_LEN].copy_from_slice(&self.magic_cookie);
+        trans_id[MAGIC_COOKIE_LEN..].copy_from_slice(&self.trans_id);
+        trans_id
+    }
+
+    // 响应包使用和请求包一样的 magic cookie 和 transaction id
+    pub fn reply(&self, msg_type: MessageType) -> Self {
+        Self {
+            msg_type,
+            msg_len: 0,
+            magic_cookie: self.magic_cookie,
+            trans_id: self.trans_id,
+        }
+    }
+
     pub fn len(&self) -> usize {
         HEADER_LEN
     }
 
     pub fn pack(&self) -> Bytes {
         let mut buf = BytesMut::with_capacity(HEADER_LEN);
-        buf.put_u16(self.msg_type);
+        self.encode(&mut buf);
+        buf.freeze()
+    }
+
+    // 写到 buf 的后面, 返回写入的长度
+    // buf 的剩余空间不够时 panic, 使用 &mut [u8] 时需要先检查长度
+    pub fn encode<B: BufMut>(&self, buf: &mut B) -> usize {
+        buf.put_u16(self.msg_type.to_u16());
         buf.put_u16(self.msg_len);
+        buf.put_slice(&self.magic_cookie);
         buf.put_slice(&self.trans_id);
-        buf.freeze()
+        HEADER_LEN
     }
 
     pub fn unpack(buf_bytes: Bytes) -> Result<Self, ParsePacketErr> {
-        let buf = buf_bytes.deref();
+        Self::unpack_from(buf_bytes.deref())
+    }
 
+    pub fn unpack_from(buf: &[u8]) -> Result<Self, ParsePacketErr> {
         // 只检查长度，不检查有效性
         if buf.len() < HEADER_LEN {
-            return Err(ParsePacketErr::BufSize(format!(
-                "header buf len:{} < {}",
-                buf.len(),
-                HEADER_LEN
-            )));
+            return Err(ParsePacketErr::BufSize {
+                expected: HEADER_LEN,
+                actual: buf.len(),
+            });
         }
 
         let mut index = 0_usize;
         let msg_type = u16::from_be_bytes([buf[index], buf[index + 1]]);
+        let msg_type = MessageType::from_u16(msg_type)?;
 
         index += 2;
         let msg_len = u16::from_be_bytes([buf[index], buf[index + 1]]);
 
         index += 2;
+        let mut magic_cookie = [0_u8; MAGIC_COOKIE_LEN];
+        magic_cookie.copy_from_slice(&buf[index..index + MAGIC_COOKIE_LEN]);
+
+        index += MAGIC_COOKIE_LEN;
         let mut trans_id = [0_u8; TRANS_ID_LEN];
-        trans_id.copy_from_slice(&buf[index..]);
+        trans_id.copy_from_slice(&buf[index..index + TRANS_ID_LEN]);
 
         Ok(Self {
             msg_type,
             msg_len,
+            magic_cookie,
             trans_id,
         })
     }
+}
 
-    pub fn validate(&self) -> Option<ValidateErr> {
-        // 检查 stun message type
-
-        if self.msg_type == MESSAGE_TYPE_BIND_REQ
-            || self.msg_type == MESSAGE_TYPE_BIND_RES
-            || self.msg_type == MESSAGE_TYPE_BIND_ERR_RES
-        {
-            return None;
+// 多行, 后面的行缩进 4 个空格
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, length: {}", self.msg_type, self.msg_len)?;
+        match self.is_classic() {
+            false => {
+                write!(f, "\n    Magic Cookie: {}", util::Hex(&self.magic_cookie))?;
+                write!(f, "\n    Transaction ID: {}", util::Hex(&self.trans_id))
+            }
+            true => write!(
+                f,
+                "\n    Transaction ID: {} (rfc 3489)",
+                util::Hex(&self.classic_trans_id())
+            ),
         }
-
-        let err_msg = format!("not support message type: {}", self.msg_type);
-        Some(ValidateErr(err_msg))
     }
 }
@@ -1,6 +1,17 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod attrs;
+pub mod auth;
+pub mod builder;
+pub mod classify;
 pub mod constants;
 pub mod error;
 pub mod header;
+pub mod message_type;
 pub mod packet;
+pub mod packet_ref;
+#[cfg(feature = "serde")]
+mod ser;
 pub mod util;
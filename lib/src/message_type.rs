@@ -0,0 +1,172 @@
+use crate::constants::*;
+use crate::error::ParsePacketErr;
+use core::fmt;
+
+// rfc 5389, 6
+//
+//  0                 1
+//  2  3  4 5 6 7 8 9 0 1 2 3 4 5
+// +--+--+-+-+-+-+-+-+-+-+-+-+-+-+
+// |M |M |M|M|M|C|M|M|M|C|M|M|M|M|
+// |11|10|9|8|7|1|6|5|4|0|3|2|1|0|
+// +--+--+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// method: 12 bit, class: 2 bit (C1 C0)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageClass {
+    Request,
+    Indication,
+    SuccessResponse,
+    ErrorResponse,
+}
+
+impl MessageClass {
+    pub fn from_bits(bits: u16) -> Self {
+        match bits & 0x03 {
+            0b00 => MessageClass::Request,
+            0b01 => MessageClass::Indication,
+            0b10 => MessageClass::SuccessResponse,
+            _ => MessageClass::ErrorResponse,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MessageClass::Request => "Request",
+            MessageClass::Indication => "Indication",
+            MessageClass::SuccessResponse => "Success Response",
+            MessageClass::ErrorResponse => "Error Response",
+        }
+    }
+
+    pub fn bits(&self) -> u16 {
+        match self {
+            MessageClass::Request => 0b00,
+            MessageClass::Indication => 0b01,
+            MessageClass::SuccessResponse => 0b10,
+            MessageClass::ErrorResponse => 0b11,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MessageType {
+    // 只有 12 bit, 由 new / from_u16 保证
+    method: u16,
+    pub class: MessageClass,
+}
+
+impl MessageType {
+    // method 超过 12 bit 时返回 BadMethod
+    pub fn new(method: u16, class: MessageClass) -> Result<Self, ParsePacketErr> {
+        if method & !MESSAGE_METHOD_MASK != 0 {
+            return Err(ParsePacketErr::BadMethod(method));
+        }
+
+        Ok(Self::new_unchecked(method, class))
+    }
+
+    // 常量使用, 调用者保证 method 不超过 12 bit
+    pub(crate) const fn new_unchecked(method: u16, class: MessageClass) -> Self {
+        Self { method, class }
+    }
+
+    pub fn method(&self) -> u16 {
+        self.method
+    }
+
+    pub fn is_request(&self) -> bool {
+        self.class == MessageClass::Request
+    }
+
+    pub fn is_indication(&self) -> bool {
+        self.class == MessageClass::Indication
+    }
+
+    pub fn is_success_response(&self) -> bool {
+        self.class == MessageClass::SuccessResponse
+    }
+
+    pub fn is_error_response(&self) -> bool {
+        self.class == MessageClass::ErrorResponse
+    }
+
+    // 不认识的 method 返回 None
+    pub fn method_name(&self) -> Option<&'static str> {
+        match self.method {
+            METHOD_BINDING => Some("Binding"),
+            METHOD_ALLOCATE => Some("Allocate"),
+            METHOD_REFRESH => Some("Refresh"),
+            METHOD_SEND => Some("Send"),
+            METHOD_DATA => Some("Data"),
+            METHOD_CREATE_PERMISSION => Some("CreatePermission"),
+            METHOD_CHANNEL_BIND => Some("ChannelBind"),
+            _ => None,
+        }
+    }
+
+    // 同一个 method 的其它 class, 例如 request -> success response
+    pub fn with_class(&self, class: MessageClass) -> Self {
+        Self::new_unchecked(self.method, class)
+    }
+
+    pub fn to_u16(&self) -> u16 {
+        let m = self.method;
+        let c = self.class.bits();
+
+        (m & 0x000f)
+            | ((m & 0x0070) << 1)
+            | ((m & 0x0f80) << 2)
+            | ((c & 0x01) << 4)
+            | ((c & 0x02) << 7)
+    }
+
+    pub fn from_u16(value: u16) -> Result<Self, ParsePacketErr> {
+        // 最高的两个 bit 必须是 0
+        if value & 0xc000 != 0 {
+            return Err(ParsePacketErr::BadMessageType(value));
+        }
+
+        let method = (value & 0x000f) | ((value & 0x00e0) >> 1) | ((value & 0x3e00) >> 2);
+        let class = ((value & 0x0010) >> 4) | ((value & 0x0100) >> 7);
+
+        Ok(Self::new_unchecked(method, MessageClass::from_bits(class)))
+    }
+}
+
+impl From<MessageType> for u16 {
+    fn from(msg_type: MessageType) -> Self {
+        msg_type.to_u16()
+    }
+}
+
+impl TryFrom<u16> for MessageType {
+    type Error = ParsePacketErr;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        MessageType::from_u16(value)
+    }
+}
+
+impl fmt::Display for MessageClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Binding Success Response (0x0101)
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.method_name() {
+            Some(v) => write!(f, "{} {} ({:#06x})", v, self.class, self.to_u16()),
+            None => write!(
+                f,
+                "Method {:#05x} {} ({:#06x})",
+                self.method,
+                self.class,
+                self.to_u16()
+            ),
+        }
+    }
+}
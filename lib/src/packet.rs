@@ -1,14 +1,19 @@
-use crate::attrs;
-use crate::attrs::address_attr::AddressAttr;
-use crate::attrs::errcode_attr::ErrcodeAttr;
-use crate::attrs::response_port::ResponsePort;
-use crate::attrs::xor_address::XorMappedAddress;
-use crate::attrs::RawAttr;
+use crate::attrs::attribute::{Attribute, TypedAttr};
+use crate::attrs::fingerprint::Fingerprint;
+use crate::attrs::message_integrity::MessageIntegrity;
+use crate::attrs::message_integrity_sha256::MessageIntegritySha256;
+use crate::attrs::registry::AttrRegistry;
+use crate::attrs::{AttrPadding, RawAttr, RawAttrRef};
+use crate::auth;
 use crate::constants::*;
 use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
 use crate::header::Header;
+use crate::packet_ref::PacketRef;
+use alloc::vec;
+use alloc::vec::Vec;
 use bytes::{BufMut, Bytes, BytesMut};
-use std::fmt::Debug;
+use core::fmt;
+use core::ops::Deref;
 
 // 是否是一个正确的stun 包
 // message_type 在范围内
@@ -16,6 +21,76 @@ use std::fmt::Debug;
 // 验证 magic cookie
 // 属性解析是否正常
 
+#[derive(Debug, Clone, Default)]
+pub struct PackOptions {
+    // 在最后添加 fingerprint (已经存在的 fingerprint 会被替换)
+    pub fingerprint: bool,
+
+    // 对端是不做 padding 的 rfc 3489 实现时, 使用 Unpadded
+    pub padding: AttrPadding,
+}
+
+// rfc 5389, 6: msg_len 的最后两位总是 0
+// rfc 3489 的实现不一定有 magic cookie, attribute 也可能不做 padding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecodeMode {
+    // attribute 必须 padding, 不能有多余的数据
+    #[default]
+    Strict,
+
+    // 先按 padding 解析, 失败时再按不 padding 解析
+    // 忽略最后不到 4 字节的数据
+    Lenient,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeOptions {
+    pub max_attrs: usize,
+
+    // 整个包的长度, 包括 header
+    pub max_size: usize,
+
+    pub mode: DecodeMode,
+
+    // 拒绝 rfc 3489 的包
+    pub require_cookie: bool,
+
+    // msg_len 必须是 4 的倍数
+    pub require_aligned: bool,
+}
+
+// 和之前 unpack 的行为一样: 接受 rfc 3489 的包, 但 attribute 必须 padding
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            max_attrs: MAX_ATTRS,
+            max_size: MAX_MESSAGE_SIZE,
+            mode: DecodeMode::Strict,
+            require_cookie: false,
+            require_aligned: false,
+        }
+    }
+}
+
+impl DecodeOptions {
+    // rfc 5389
+    pub fn strict() -> Self {
+        Self {
+            require_cookie: true,
+            require_aligned: true,
+            ..Default::default()
+        }
+    }
+
+    // rfc 3489
+    pub fn lenient() -> Self {
+        Self {
+            mode: DecodeMode::Lenient,
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Packet {
     pub header: Header,
@@ -29,9 +104,14 @@ impl Packet {
         packet
     }
 
+    // 超过 u16 时保存 u16::MAX, 打包时返回 TooLarge
     fn update_header_len(&mut self) {
         let total = self.attrs.iter().fold(0_usize, |acc, x| acc + x.len());
-        self.header.msg_len = total as u16;
+        self.header.msg_len = u16::try_from(total).unwrap_or(u16::MAX);
+    }
+
+    pub fn is_classic(&self) -> bool {
+        self.header.is_classic()
     }
 
     pub fn add_attr(&mut self, attr: RawAttr) {
@@ -44,103 +124,308 @@ impl Packet {
         self.update_header_len();
     }
 
-    pub fn pack(&self) -> Bytes {
-        let mut buf = BytesMut::new();
-        buf.put_slice(&self.header.pack());
+    pub fn has_attr(&self, attr_type: u16) -> bool {
+        self.attrs.iter().any(|x| x.attr_type == attr_type)
+    }
+
+    // 第一个类型为 T 的 attribute, 没有时返回 Ok(None)
+    pub fn get<T: TypedAttr>(&self) -> Result<Option<T>, ParsePacketErr> {
+        match self.attrs.iter().find(|x| T::is_type(x.attr_type)) {
+            Some(v) => T::decode(v, &self.header).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // 同一个类型对应多个 attr_type 时使用, 例如 AddressAttr
+    pub fn get_by_type<T: TypedAttr>(&self, attr_type: u16) -> Result<Option<T>, ParsePacketErr> {
+        if !T::is_type(attr_type) {
+            return Ok(None);
+        }
+
+        match self.attrs.iter().find(|x| x.attr_type == attr_type) {
+            Some(v) => T::decode(v, &self.header).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // 按顺序解析每个 attribute, 只有在迭代时才解析
+    pub fn attributes(&self) -> impl Iterator<Item = Result<Attribute, ParsePacketErr>> + '_ {
+        self.attrs
+            .iter()
+            .map(|x| Attribute::decode(x, &self.header))
+    }
+
+    // registry 中注册的 attribute 解析成 Attribute::Custom
+    pub fn attributes_with<'a>(
+        &'a self,
+        registry: &'a AttrRegistry,
+    ) -> impl Iterator<Item = Result<Attribute, ParsePacketErr>> + 'a {
+        self.attrs
+            .iter()
+            .map(|x| Attribute::decode_with(x, &self.header, registry))
+    }
+
+    // 和 Display 一样, registry 中注册的 attribute 使用自定义的名字和输出
+    pub fn display_with<'a>(&'a self, registry: &'a AttrRegistry) -> impl fmt::Display + 'a {
+        PacketDisplay {
+            packet: self,
+            registry,
+        }
+    }
+
+    // rfc 5389, 7.3
+    // 不认识的 comprehension-required attribute, comprehension-optional 的忽略
+    pub fn unknown_attrs(&self) -> Vec<u16> {
+        self.unknown_attrs_with(&AttrRegistry::new())
+    }
+
+    // registry 中注册的 attribute 也是认识的
+    pub fn unknown_attrs_with(&self, registry: &AttrRegistry) -> Vec<u16> {
+        let mut list: Vec<u16> = vec![];
         for v in self.attrs.iter() {
-            buf.put_slice(&v.pack());
+            if !v.is_comprehension_required()
+                || Attribute::is_known(v.attr_type)
+                || registry.contains(v.attr_type)
+                || list.contains(&v.attr_type)
+            {
+                continue;
+            }
+            list.push(v.attr_type);
+        }
+        list
+    }
+
+    // rfc 5389, 15.4
+    // 使用当前所有的 attribute 计算 message-integrity, 并添加到最后
+    // 之后只能再添加 fingerprint
+    pub fn add_message_integrity(&mut self, key: &[u8]) -> Result<(), ParsePacketErr> {
+        let input = self.partial_input(self.attrs.len(), MESSAGE_INTEGRITY_LEN)?;
+        let hmac = auth::hmac_sha1(key, &input);
+        self.add_attr(MessageIntegrity::new(hmac).into());
+        Ok(())
+    }
+
+    // rfc 8489, 14.6
+    // 如果同时需要 message-integrity, 要先添加 message-integrity
+    pub fn add_message_integrity_sha256(&mut self, key: &[u8]) -> Result<(), ParsePacketErr> {
+        let input = self.partial_input(self.attrs.len(), MESSAGE_INTEGRITY_SHA256_LEN)?;
+        let hmac = auth::hmac_sha256(key, &input);
+        self.add_attr(MessageIntegritySha256::new(Bytes::copy_from_slice(&hmac)).into());
+        Ok(())
+    }
+
+    pub fn verify_message_integrity(&self, key: &[u8]) -> Option<ValidateErr> {
+        let index = match self.find_attr_index(ATTR_MESSAGE_INTEGRITY) {
+            None => return Some(ValidateErr::MissingAttr(ATTR_MESSAGE_INTEGRITY)),
+            Some(v) => v,
+        };
+
+        let attr: MessageIntegrity = match self.attrs[index].clone().try_into() {
+            Ok(v) => v,
+            Err(e) => return Some(e.into()),
+        };
+
+        let input = match self.partial_input(index, MESSAGE_INTEGRITY_LEN) {
+            Ok(v) => v,
+            Err(e) => return Some(e.into()),
+        };
+        if auth::verify_hmac_sha1(key, &input, &attr.hmac) {
+            return None;
+        }
+
+        Some(ValidateErr::IntegrityMismatch(ATTR_MESSAGE_INTEGRITY))
+    }
+
+    pub fn verify_message_integrity_sha256(&self, key: &[u8]) -> Option<ValidateErr> {
+        let index = match self.find_attr_index(ATTR_MESSAGE_INTEGRITY_SHA256) {
+            None => return Some(ValidateErr::MissingAttr(ATTR_MESSAGE_INTEGRITY_SHA256)),
+            Some(v) => v,
+        };
+
+        let attr: MessageIntegritySha256 = match self.attrs[index].clone().try_into() {
+            Ok(v) => v,
+            Err(e) => return Some(e.into()),
+        };
+
+        let input = match self.partial_input(index, attr.hmac.len()) {
+            Ok(v) => v,
+            Err(e) => return Some(e.into()),
+        };
+        if auth::verify_hmac_sha256(key, &input, attr.hmac.deref()) {
+            return None;
         }
 
-        buf.freeze()
+        Some(ValidateErr::IntegrityMismatch(
+            ATTR_MESSAGE_INTEGRITY_SHA256,
+        ))
+    }
+
+    // rfc 5389, 15.5
+    // 必须是最后一个 attribute
+    pub fn add_fingerprint(&mut self) -> Result<(), ParsePacketErr> {
+        let input = self.partial_input(self.attrs.len(), FINGERPRINT_LEN)?;
+        self.add_attr(Fingerprint::calculate(&input).into());
+        Ok(())
     }
 
-    pub fn unpack(mut buf_bytes: Bytes) -> Result<Self, ParsePacketErr> {
-        if buf_bytes.len() < HEADER_LEN {
-            return Err(ParsePacketErr::BufSize(format!(
-                "header buf len:{} < {}",
-                buf_bytes.len(),
-                HEADER_LEN
-            )));
+    // 没有 fingerprint 时返回 None
+    pub fn verify_fingerprint(&self) -> Option<ValidateErr> {
+        let index = self.find_attr_index(ATTR_FINGERPRINT)?;
+        if index != self.attrs.len() - 1 {
+            return Some(ValidateErr::FingerprintNotLast);
         }
 
-        let header_buf = buf_bytes.split_to(HEADER_LEN);
-        let header = Header::unpack(header_buf)?;
-        let origin_header_len = header.msg_len;
+        let attr: Fingerprint = match self.attrs[index].clone().try_into() {
+            Ok(v) => v,
+            Err(e) => return Some(e.into()),
+        };
 
-        if header.msg_len as usize != buf_bytes.len() {
-            return Err(ParsePacketErr::NotMatch(format!(
-                "header len:{} != {}",
-                header.msg_len,
-                buf_bytes.len()
-            )));
+        let input = match self.partial_input(index, FINGERPRINT_LEN) {
+            Ok(v) => v,
+            Err(e) => return Some(e.into()),
+        };
+        if Fingerprint::calculate(&input).crc == attr.crc {
+            return None;
         }
 
-        let mut attr_list = vec![];
+        Some(ValidateErr::FingerprintMismatch)
+    }
+
+    fn find_attr_index(&self, attr_type: u16) -> Option<usize> {
+        self.attrs.iter().position(|x| x.attr_type == attr_type)
+    }
 
-        let mut max_attr = 32_usize;
+    // header + attrs[..index], 用于计算 message-integrity / fingerprint
+    // header 的 msg_len 需要包括后面要添加的 attribute 本身 (value 长度: value_len)
+    fn partial_input(&self, index: usize, value_len: usize) -> Result<BytesMut, ParsePacketErr> {
+        let attrs = &self.attrs[..index];
+        let attrs_len = attrs.iter().fold(0_usize, |acc, x| acc + x.len());
 
-        while buf_bytes.len() >= 4 {
-            if max_attr == 0 {
-                return Err(ParsePacketErr::TooManyAttrs);
-            }
+        let mut header = self.header.clone();
+        header.msg_len = to_msg_len(attrs_len + 4 + value_len)?;
+
+        let mut buf = BytesMut::with_capacity(HEADER_LEN + attrs_len);
+        header.encode(&mut buf);
+        for v in attrs.iter() {
+            v.encode(&mut buf, AttrPadding::Padded);
+        }
+
+        Ok(buf)
+    }
+
+    // 按 options 打包之后的长度, 包括 header
+    pub fn encoded_len(&self, options: &PackOptions) -> usize {
+        let mut msg_len = self
+            .attrs
+            .iter()
+            .filter(|x| !(options.fingerprint && x.attr_type == ATTR_FINGERPRINT))
+            .fold(0_usize, |acc, x| acc + x.len_with(options.padding));
+        if options.fingerprint {
+            msg_len += 4 + FINGERPRINT_LEN;
+        }
+
+        HEADER_LEN + msg_len
+    }
+
+    // 写到 buf 的后面, 返回写入的长度
+    // buf 的剩余空间不够时返回 BufSize, msg_len 超过 u16 时返回 TooLarge, 都不会写入
+    pub fn encode<B: BufMut>(
+        &self,
+        buf: &mut B,
+        options: &PackOptions,
+    ) -> Result<usize, ParsePacketErr> {
+        let padding = options.padding;
+        let total = self.encoded_len(options);
+
+        let mut header = self.header.clone();
+        header.msg_len = to_msg_len(total - HEADER_LEN)?;
 
-            let attr_len = u16::from_be_bytes([buf_bytes[2], buf_bytes[3]]);
+        if buf.remaining_mut() < total {
+            return Err(ParsePacketErr::BufSize {
+                expected: total,
+                actual: buf.remaining_mut(),
+            });
+        }
+
+        // fingerprint 的 crc 边写边算, buf 不需要可读
+        let mut hasher = crc32fast::Hasher::new();
+        let mut head = [0_u8; HEADER_LEN];
+        header.encode(&mut &mut head[..]);
+        buf.put_slice(&head);
+        hasher.update(&head);
 
-            if buf_bytes.len() < attr_len as usize + 4 {
-                return Err(ParsePacketErr::BufSize(format!(
-                    "attr buf len:{} < {}",
-                    buf_bytes.len(),
-                    attr_len + 4
-                )));
+        for v in self.attrs.iter() {
+            if options.fingerprint && v.attr_type == ATTR_FINGERPRINT {
+                continue;
             }
-            let attr_buf = buf_bytes.split_to(attr_len as usize + 4);
-            let attr = RawAttr::unpack(attr_buf)?;
-            attr_list.push(attr);
 
-            max_attr -= 1;
+            v.encode(buf, padding);
+            if options.fingerprint {
+                hasher.update(&v.attr_type.to_be_bytes());
+                hasher.update(&v.attr_len.to_be_bytes());
+                hasher.update(&v.value);
+                hasher.update(v.padding_bytes(padding));
+            }
         }
 
-        let packet = Packet::new(header, attr_list);
-        if packet.header.msg_len != origin_header_len {
-            return Err(ParsePacketErr::NotMatch(format!(
-                "packet data len:{} != packet msg len:{}",
-                packet.header.msg_len, origin_header_len
-            )));
+        if options.fingerprint {
+            let attr: RawAttr = Fingerprint::new(hasher.finalize() ^ FINGERPRINT_XOR).into();
+            attr.encode(buf, padding);
         }
 
-        Ok(packet)
+        Ok(total)
+    }
+
+    // buf 的长度不够时返回 BufSize
+    pub fn encode_to_slice(
+        &self,
+        buf: &mut [u8],
+        options: &PackOptions,
+    ) -> Result<usize, ParsePacketErr> {
+        self.encode(&mut &mut buf[..], options)
+    }
+
+    pub fn pack_with(&self, options: &PackOptions) -> Result<Bytes, ParsePacketErr> {
+        let mut buf = BytesMut::with_capacity(self.encoded_len(options));
+        self.encode(&mut buf, options)?;
+        Ok(buf.freeze())
+    }
+
+    pub fn pack(&self) -> Result<Bytes, ParsePacketErr> {
+        self.pack_with(&PackOptions::default())
+    }
+
+    pub fn unpack(buf_bytes: Bytes) -> Result<Self, ParsePacketErr> {
+        Packet::unpack_with(buf_bytes, &DecodeOptions::default())
+    }
+
+    // attribute 的 value 和 buf_bytes 共享内存
+    pub fn unpack_with(buf_bytes: Bytes, options: &DecodeOptions) -> Result<Self, ParsePacketErr> {
+        let packet = PacketRef::parse_with(&buf_bytes, options)?;
+        Ok(packet.to_packet_in(&buf_bytes))
     }
 
     pub fn validate(&self) -> Option<ValidateErr> {
-        if let Some(v) = self.header.validate() {
+        self.validate_with(&AttrRegistry::new())
+    }
+
+    // registry 中注册的 attribute 使用自定义的检查, 其它的使用内置的检查
+    pub fn validate_with(&self, registry: &AttrRegistry) -> Option<ValidateErr> {
+        if let Some(v) = self.verify_fingerprint() {
             return Some(v);
         }
 
         for v in self.attrs.iter() {
-            if AddressAttr::is_like_mapped_addr(v.attr_type) {
-                if let Some(e) = validate_attr::<AddressAttr>(v) {
-                    return Some(e);
-                }
-            }
-            if v.attr_type == ATTR_ERROR_CODE {
-                if let Some(e) = validate_attr::<ErrcodeAttr>(v) {
-                    return Some(e);
-                }
-            }
-            if v.attr_type == ATTR_RESPONSE_PORT {
-                if let Some(e) = validate_attr::<ResponsePort>(v) {
-                    return Some(e);
-                }
-            }
-            if v.attr_type == ATTR_XOR_MAPPED_ADDRESS {
-                let xor = match XorMappedAddress::from_base_attr(v.clone(), &self.header.trans_id) {
-                    Ok(v) => v,
-                    Err(e) => return Some(ValidateErr(format!("{:?}", e))),
-                };
-
-                if let Some(e) = xor.validate() {
-                    return Some(e);
-                }
+            let result = match registry.validate(v, &self.header) {
+                Some(r) => r,
+                None => match Attribute::decode(v, &self.header) {
+                    Ok(attr) => attr.validate(),
+                    Err(e) => Some(e.into()),
+                },
+            };
+
+            if result.is_some() {
+                return result;
             }
         }
 
@@ -148,14 +433,59 @@ impl Packet {
     }
 }
 
-fn validate_attr<T>(raw_attr: &RawAttr) -> Option<ValidateErr>
-where
-    T: AttrValidator + TryFrom<RawAttr>,
-    <T as std::convert::TryFrom<attrs::RawAttr>>::Error: Debug,
-{
-    let attr: Result<T, _> = raw_attr.clone().try_into();
-    match attr {
-        Ok(v) => v.validate(),
-        Err(e) => Some(ValidateErr(format!("{:?}", e))),
+// rfc 5389, 6: msg_len 只有 16 bit
+pub(crate) fn to_msg_len(len: usize) -> Result<u16, ParsePacketErr> {
+    match u16::try_from(len) {
+        Ok(v) => Ok(v),
+        Err(_e) => Err(ParsePacketErr::TooLarge {
+            max: HEADER_LEN + u16::MAX as usize,
+            actual: HEADER_LEN + len,
+        }),
+    }
+}
+
+// 类似 wireshark 的树形输出
+//
+// Binding Request (0x0001), length: 8
+//     Magic Cookie: 2112a442
+//     Transaction ID: 0102030405060708090a0b0c
+//     Attributes:
+//         CHANGE-REQUEST (0x0003), 4 bytes: change ip: false, change port: true
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display_with(&AttrRegistry::new()))
+    }
+}
+
+struct PacketDisplay<'a> {
+    packet: &'a Packet,
+    registry: &'a AttrRegistry,
+}
+
+impl fmt::Display for PacketDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let attrs = self.packet.attrs.iter().map(RawAttrRef::from);
+        fmt_packet(f, &self.packet.header, attrs, self.registry)
+    }
+}
+
+pub(crate) fn fmt_packet<'a>(
+    f: &mut fmt::Formatter<'_>,
+    header: &Header,
+    attrs: impl Iterator<Item = RawAttrRef<'a>>,
+    registry: &AttrRegistry,
+) -> fmt::Result {
+    write!(f, "{}", header)?;
+
+    let mut attrs = attrs.peekable();
+    if attrs.peek().is_none() {
+        return Ok(());
+    }
+
+    write!(f, "\n    Attributes:")?;
+    for v in attrs {
+        write!(f, "\n        ")?;
+        Attribute::fmt_line(f, v, header, registry)?;
     }
+    Ok(())
 }
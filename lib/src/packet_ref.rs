@@ -0,0 +1,294 @@
+use crate::attrs::attribute::{Attribute, TypedAttr};
+use crate::attrs::fingerprint::Fingerprint;
+use crate::attrs::registry::AttrRegistry;
+use crate::attrs::{AttrPadding, RawAttr, RawAttrRef};
+use crate::constants::*;
+use crate::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use crate::header::Header;
+use crate::packet::{self, DecodeMode, DecodeOptions, Packet};
+use bytes::Bytes;
+use core::fmt;
+
+// 借用接收 buf 的 stun 包, 解析时只检查 header 和每个 attribute 的长度, 不分配内存
+// attribute 在访问的时候才解析
+//
+// 需要保存或者修改时, 使用 to_packet
+
+#[derive(Debug, Clone)]
+pub struct PacketRef<'a> {
+    pub header: Header,
+
+    // header 之后的所有 attribute
+    data: &'a [u8],
+    padding: AttrPadding,
+}
+
+impl<'a> PacketRef<'a> {
+    pub fn parse(buf: &'a [u8]) -> Result<Self, ParsePacketErr> {
+        Self::parse_with(buf, &DecodeOptions::default())
+    }
+
+    pub fn parse_with(buf: &'a [u8], options: &DecodeOptions) -> Result<Self, ParsePacketErr> {
+        if buf.len() > options.max_size {
+            return Err(ParsePacketErr::TooLarge {
+                max: options.max_size,
+                actual: buf.len(),
+            });
+        }
+
+        if buf.len() < HEADER_LEN {
+            return Err(ParsePacketErr::BufSize {
+                expected: HEADER_LEN,
+                actual: buf.len(),
+            });
+        }
+
+        let header = Header::unpack_from(&buf[..HEADER_LEN])?;
+
+        if options.require_cookie && header.is_classic() {
+            return Err(ParsePacketErr::NoMagicCookie);
+        }
+
+        if options.require_aligned && !header.msg_len.is_multiple_of(4) {
+            return Err(ParsePacketErr::MsgLenNotAligned(header.msg_len as usize));
+        }
+
+        if header.msg_len as usize != buf.len() - HEADER_LEN {
+            return Err(ParsePacketErr::MsgLen {
+                msg_len: header.msg_len as usize,
+                actual: buf.len() - HEADER_LEN,
+            });
+        }
+
+        match options.mode {
+            DecodeMode::Strict => Self::parse_attrs(buf, header, AttrPadding::Padded, options),
+            DecodeMode::Lenient => {
+                // 两种都失败时返回按 padding 解析的错误
+                match Self::parse_attrs(buf, header.clone(), AttrPadding::Padded, options) {
+                    Ok(v) => Ok(v),
+                    Err(e) => Self::parse_attrs(buf, header, AttrPadding::Unpadded, options)
+                        .map_err(|_| e),
+                }
+            }
+        }
+    }
+
+    fn parse_attrs(
+        buf: &'a [u8],
+        header: Header,
+        padding: AttrPadding,
+        options: &DecodeOptions,
+    ) -> Result<Self, ParsePacketErr> {
+        let data = &buf[HEADER_LEN..];
+
+        let mut count = 0_usize;
+        let mut offset = 0_usize;
+        let mut fingerprint: Option<(usize, RawAttrRef)> = None;
+
+        while data.len() - offset >= 4 {
+            if count == options.max_attrs {
+                return Err(ParsePacketErr::TooManyAttrs {
+                    max: options.max_attrs,
+                });
+            }
+
+            if let Some((index, _)) = fingerprint {
+                return Err(ParsePacketErr::FingerprintNotLast {
+                    offset: HEADER_LEN + index,
+                });
+            }
+
+            let (attr, len) = next_attr(data, offset, padding)?;
+            if attr.attr_type == ATTR_FINGERPRINT {
+                fingerprint = Some((offset, attr));
+            }
+
+            offset += len;
+            count += 1;
+        }
+
+        if offset != data.len() && options.mode == DecodeMode::Strict {
+            return Err(ParsePacketErr::TrailingBytes {
+                offset: HEADER_LEN + offset,
+                len: data.len() - offset,
+            });
+        }
+
+        // fingerprint 必须是最后一个, 使用收到的原始数据计算
+        if let Some((index, attr)) = fingerprint {
+            let attr: Fingerprint = attr.try_into()?;
+            let expected = Fingerprint::calculate(&buf[..HEADER_LEN + index]).crc;
+            if expected != attr.crc {
+                return Err(ParsePacketErr::FingerprintMismatch {
+                    expected,
+                    actual: attr.crc,
+                });
+            }
+        }
+
+        Ok(Self {
+            header,
+            data: &data[..offset],
+            padding,
+        })
+    }
+
+    pub fn is_classic(&self) -> bool {
+        self.header.is_classic()
+    }
+
+    pub fn attrs(&self) -> AttrIter<'a> {
+        AttrIter {
+            data: self.data,
+            padding: self.padding,
+        }
+    }
+
+    pub fn has_attr(&self, attr_type: u16) -> bool {
+        self.attrs().any(|x| x.attr_type == attr_type)
+    }
+
+    pub fn get<T: TypedAttr>(&self) -> Result<Option<T>, ParsePacketErr> {
+        match self.attrs().find(|x| T::is_type(x.attr_type)) {
+            Some(v) => T::decode_ref(v, &self.header).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_by_type<T: TypedAttr>(&self, attr_type: u16) -> Result<Option<T>, ParsePacketErr> {
+        if !T::is_type(attr_type) {
+            return Ok(None);
+        }
+
+        match self.attrs().find(|x| x.attr_type == attr_type) {
+            Some(v) => T::decode_ref(v, &self.header).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn attributes(&self) -> impl Iterator<Item = Result<Attribute, ParsePacketErr>> + '_ {
+        self.attrs().map(|x| Attribute::decode_ref(x, &self.header))
+    }
+
+    // rfc 5389, 7.3
+    // 是否有不认识的 comprehension-required attribute
+    pub fn has_unknown_attrs(&self) -> bool {
+        self.has_unknown_attrs_with(&AttrRegistry::new())
+    }
+
+    // registry 中注册的 attribute 也是认识的
+    pub fn has_unknown_attrs_with(&self, registry: &AttrRegistry) -> bool {
+        self.attrs().any(|x| {
+            x.is_comprehension_required()
+                && !Attribute::is_known(x.attr_type)
+                && !registry.contains(x.attr_type)
+        })
+    }
+
+    // 和 Packet::validate 一样, fingerprint 已经在 parse 的时候检查过
+    pub fn validate(&self) -> Option<ValidateErr> {
+        self.validate_with(&AttrRegistry::new())
+    }
+
+    // registry 中注册的 attribute 使用自定义的检查, 需要复制一份 value
+    pub fn validate_with(&self, registry: &AttrRegistry) -> Option<ValidateErr> {
+        for v in self.attrs() {
+            let result = match registry.contains(v.attr_type) {
+                true => registry.validate(&v.to_owned(), &self.header).flatten(),
+                false => match Attribute::decode_ref(v, &self.header) {
+                    Ok(attr) => attr.validate(),
+                    Err(e) => Some(e.into()),
+                },
+            };
+
+            if result.is_some() {
+                return result;
+            }
+        }
+
+        None
+    }
+
+    pub fn to_packet(&self) -> Packet {
+        let attrs = self.attrs().map(|x| x.to_owned()).collect();
+        Packet::new(self.header.clone(), attrs)
+    }
+
+    // 和 buf_bytes 共享内存, buf_bytes 必须是 parse 时使用的 buf
+    pub(crate) fn to_packet_in(&self, buf_bytes: &Bytes) -> Packet {
+        let attrs = self
+            .attrs()
+            .map(|x| {
+                RawAttr::with_padding(
+                    x.attr_type,
+                    buf_bytes.slice_ref(x.value),
+                    buf_bytes.slice_ref(x.padding),
+                )
+            })
+            .collect();
+        Packet::new(self.header.clone(), attrs)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AttrIter<'a> {
+    data: &'a [u8],
+    padding: AttrPadding,
+}
+
+impl<'a> Iterator for AttrIter<'a> {
+    type Item = RawAttrRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < 4 {
+            return None;
+        }
+
+        // parse 的时候已经检查过长度
+        match next_attr(self.data, 0, self.padding) {
+            Ok((attr, len)) => {
+                self.data = &self.data[len..];
+                Some(attr)
+            }
+            Err(_) => {
+                self.data = &[];
+                None
+            }
+        }
+    }
+}
+
+// 返回 data[offset..] 的第一个 attribute 和包括 padding 的长度
+fn next_attr(
+    data: &[u8],
+    offset: usize,
+    padding: AttrPadding,
+) -> Result<(RawAttrRef<'_>, usize), ParsePacketErr> {
+    let buf = &data[offset..];
+    let attr_type = u16::from_be_bytes([buf[0], buf[1]]);
+    let attr_len = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+
+    // value 和 padding 都要在 buf 里
+    let expected = 4 + attr_len + padding.padding_len(attr_len);
+    if buf.len() < expected {
+        return Err(ParsePacketErr::AttrTruncated {
+            attr_type,
+            offset: HEADER_LEN + offset,
+            expected,
+            actual: buf.len(),
+        });
+    }
+
+    let attr = RawAttrRef::with_padding(
+        attr_type,
+        &buf[4..4 + attr_len],
+        &buf[4 + attr_len..expected],
+    );
+    Ok((attr, expected))
+}
+
+impl fmt::Display for PacketRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        packet::fmt_packet(f, &self.header, self.attrs(), &AttrRegistry::new())
+    }
+}
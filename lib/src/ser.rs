@@ -0,0 +1,165 @@
+use crate::attrs::attribute::Attribute;
+use crate::attrs::RawAttr;
+use crate::message_type::{MessageClass, MessageType};
+use crate::packet::Packet;
+use crate::util::Hex;
+use core::fmt;
+use serde::ser::{SerializeSeq, SerializeStruct};
+use serde::{Serialize, Serializer};
+
+// serde feature
+// 给调试用, 输出人能直接看的格式:
+// method/class/attribute 用名字, 地址是解码后的 (xor 已经还原), 二进制数据用 hex
+
+// 使用 Display 输出成字符串
+struct Str<T>(T);
+
+impl<T: fmt::Display> Serialize for Str<T> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(&self.0)
+    }
+}
+
+// attr type, method 等, 0x 开头
+struct HexU16(u16);
+
+impl fmt::Display for HexU16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06x}", self.0)
+    }
+}
+
+struct HexU32(u32);
+
+impl fmt::Display for HexU32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010x}", self.0)
+    }
+}
+
+// 下面几个给 #[serde(serialize_with)] 使用
+
+pub(crate) fn hex<T: AsRef<[u8]>, S: Serializer>(v: &T, s: S) -> Result<S::Ok, S::Error> {
+    Str(Hex(v.as_ref())).serialize(s)
+}
+
+pub(crate) fn hex_u16<S: Serializer>(v: &u16, s: S) -> Result<S::Ok, S::Error> {
+    Str(HexU16(*v)).serialize(s)
+}
+
+pub(crate) fn hex_u32<S: Serializer>(v: &u32, s: S) -> Result<S::Ok, S::Error> {
+    Str(HexU32(*v)).serialize(s)
+}
+
+pub(crate) fn hex_u16_seq<S: Serializer>(v: &[u16], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(v.iter().map(|x| Str(HexU16(*x))))
+}
+
+// 地址等
+pub(crate) fn display<T: fmt::Display, S: Serializer>(v: &T, s: S) -> Result<S::Ok, S::Error> {
+    Str(v).serialize(s)
+}
+
+//--------------------------------------------------
+
+impl Serialize for MessageClass {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(self.name())
+    }
+}
+
+// 不认识的 method 输出 hex
+impl Serialize for MessageType {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut state = s.serialize_struct("MessageType", 3)?;
+        match self.method_name() {
+            Some(v) => state.serialize_field("method", v)?,
+            None => state.serialize_field("method", &Str(HexU16(self.method())))?,
+        }
+        state.serialize_field("class", &self.class)?;
+        state.serialize_field("value", &Str(HexU16(self.to_u16())))?;
+        state.end()
+    }
+}
+
+// 不认识或者解析失败的 attribute, value 是 hex
+impl Serialize for RawAttr {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut state = s.serialize_struct("RawAttr", 4)?;
+        state.serialize_field("name", Attribute::name(self.attr_type).unwrap_or("UNKNOWN"))?;
+        state.serialize_field("type", &Str(HexU16(self.attr_type)))?;
+        state.serialize_field("length", &self.attr_len)?;
+        state.serialize_field("value", &Str(Hex(&self.value)))?;
+        state.end()
+    }
+}
+
+impl Serialize for Attribute {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let raw = match self {
+            Attribute::Unknown(v) => {
+                return v.serialize(s);
+            }
+            _ => self.attr_type(),
+        };
+
+        let mut state = s.serialize_struct("Attribute", 3)?;
+        state.serialize_field("name", self.type_name())?;
+        state.serialize_field("type", &Str(HexU16(raw)))?;
+        match self {
+            Attribute::MappedAddress(v)
+            | Attribute::SourceAddress(v)
+            | Attribute::ChangedAddress(v)
+            | Attribute::ResponseOrigin(v)
+            | Attribute::OtherAddress(v)
+            | Attribute::AlternateServer(v) => state.serialize_field("value", &Str(v.address))?,
+            Attribute::XorMappedAddress(v) => state.serialize_field("value", &Str(v.address))?,
+            Attribute::ChangeRequest(v) => state.serialize_field("value", v)?,
+            Attribute::Username(v) => state.serialize_field("value", &v.username)?,
+            Attribute::MessageIntegrity(v) => state.serialize_field("value", &Str(Hex(&v.hmac)))?,
+            Attribute::ErrorCode(v) => state.serialize_field("value", v)?,
+            Attribute::UnknownAttributes(v) => state.serialize_field("value", v)?,
+            Attribute::Realm(v) => state.serialize_field("value", &v.realm)?,
+            Attribute::Nonce(v) => state.serialize_field("value", &v.nonce)?,
+            Attribute::MessageIntegritySha256(v) => {
+                state.serialize_field("value", &Str(Hex(&v.hmac)))?
+            }
+            Attribute::PasswordAlgorithm(v) => state.serialize_field("value", v)?,
+            Attribute::Userhash(v) => state.serialize_field("value", &Str(Hex(&v.hash)))?,
+            Attribute::Padding(v) => state.serialize_field("value", &Str(Hex(&v.data)))?,
+            Attribute::ResponsePort(v) => state.serialize_field("value", &v.port)?,
+            Attribute::PasswordAlgorithms(v) => state.serialize_field("value", &v.algorithms)?,
+            Attribute::Software(v) => state.serialize_field("value", &v.software)?,
+            Attribute::Fingerprint(v) => state.serialize_field("value", &Str(HexU32(v.crc)))?,
+            Attribute::Custom(v) => state.serialize_field("value", &Str(v))?,
+            Attribute::Unknown(_) => {}
+        }
+        state.end()
+    }
+}
+
+// attribute 按 header 解析 (xor-mapped-address 需要 transaction id), 解析失败的输出原始数据
+impl Serialize for Packet {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut state = s.serialize_struct("Packet", 2)?;
+        state.serialize_field("header", &self.header)?;
+        state.serialize_field("attributes", &Attributes(self))?;
+        state.end()
+    }
+}
+
+struct Attributes<'a>(&'a Packet);
+
+impl Serialize for Attributes<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let packet = self.0;
+        let mut seq = s.serialize_seq(Some(packet.attrs.len()))?;
+        for raw in packet.attrs.iter() {
+            match Attribute::decode(raw, &packet.header) {
+                Ok(v) => seq.serialize_element(&v)?,
+                Err(_) => seq.serialize_element(raw)?,
+            }
+        }
+        seq.end()
+    }
+}
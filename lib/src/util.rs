@@ -1,10 +1,14 @@
 use crate::attrs::padding_attr::PaddingAttr;
-use crate::constants::{MAGIC_COOKIE, TRANS_ID_LEN};
-use crate::header::TransId;
+use crate::constants::{CLASSIC_TRANS_ID_LEN, MAGIC_COOKIE_LEN, TRANS_ID_LEN};
+use crate::header::{ClassicTransId, TransId};
+use crate::packet::DecodeOptions;
+use crate::packet_ref::PacketRef;
+use alloc::format;
+use alloc::string::{String, ToString};
 use bytes::{BufMut, BytesMut};
-use rand::prelude::*;
-use std::fmt::Write as _;
-use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use core::fmt::{self, Write as _};
+use core::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use rand::RngCore;
 
 pub fn print_bytes(buf: &[u8], separator: &str, row_width: usize) -> String {
     let mut hex = String::new();
@@ -21,51 +25,96 @@ pub fn print_bytes(buf: &[u8], separator: &str, row_width: usize) -> String {
     hex
 }
 
+// 连续的 hex, 没有分隔符, 用在 Display 中
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for v in self.0 {
+            write!(f, "{:02x}", v)?;
+        }
+        Ok(())
+    }
+}
+
+// 能解析成 stun 包时输出 Packet 的 Display, 否则输出 hex
+pub fn print_packet(buf: &[u8]) -> String {
+    match PacketRef::parse_with(buf, &DecodeOptions::lenient()) {
+        Ok(v) => v.to_string(),
+        Err(e) => format!("{}\n{}", e, print_bytes(buf, " ", 8)),
+    }
+}
+
+// 96 bit, 不包括 magic cookie
+#[cfg(feature = "std")]
 pub fn new_trans_id() -> TransId {
-    let cookie_len = MAGIC_COOKIE.len();
+    new_trans_id_with(&mut rand::thread_rng())
+}
+
+// rfc 3489, 128 bit
+#[cfg(feature = "std")]
+pub fn new_classic_trans_id() -> ClassicTransId {
+    new_classic_trans_id_with(&mut rand::thread_rng())
+}
+
+// no_std 时使用设备自己的随机数生成器
+pub fn new_trans_id_with<R: RngCore + ?Sized>(rng: &mut R) -> TransId {
     let mut trans_id = [0u8; TRANS_ID_LEN];
+    rng.fill_bytes(&mut trans_id);
+    trans_id
+}
 
-    trans_id[..cookie_len].copy_from_slice(&MAGIC_COOKIE[..]);
-    rand::thread_rng().fill_bytes(&mut trans_id[4..]);
+pub fn new_classic_trans_id_with<R: RngCore + ?Sized>(rng: &mut R) -> ClassicTransId {
+    let mut trans_id = [0u8; CLASSIC_TRANS_ID_LEN];
+    rng.fill_bytes(&mut trans_id);
     trans_id
 }
 
-pub fn xor_address_v4(addr: SocketAddrV4) -> SocketAddrV4 {
+// magic_cookie: rfc 5389 的包是 MAGIC_COOKIE, rfc 3489 的包是 transaction id 的前4个字节
+pub fn xor_address_v4(addr: SocketAddrV4, magic_cookie: &[u8; MAGIC_COOKIE_LEN]) -> SocketAddrV4 {
     let port = addr.port();
-    let magic_prefix = u16::from_be_bytes([MAGIC_COOKIE[0], MAGIC_COOKIE[1]]);
+    let magic_prefix = u16::from_be_bytes([magic_cookie[0], magic_cookie[1]]);
     let port = port ^ magic_prefix;
 
     let src_buf = addr.ip().octets();
     let mut buf = [0_u8; 4];
     for i in 0..buf.len() {
-        buf[i] = src_buf[i] ^ MAGIC_COOKIE[i];
+        buf[i] = src_buf[i] ^ magic_cookie[i];
     }
 
     SocketAddrV4::new(Ipv4Addr::from(buf), port)
 }
 
-pub fn xor_address_v6(addr: SocketAddrV6, trans_id: &TransId) -> SocketAddrV6 {
+pub fn xor_address_v6(
+    addr: SocketAddrV6,
+    magic_cookie: &[u8; MAGIC_COOKIE_LEN],
+    trans_id: &TransId,
+) -> SocketAddrV6 {
     let port = addr.port();
-    let magic_prefix = u16::from_be_bytes([MAGIC_COOKIE[0], MAGIC_COOKIE[1]]);
+    let magic_prefix = u16::from_be_bytes([magic_cookie[0], magic_cookie[1]]);
     let port = port ^ magic_prefix;
 
     let src_buf = addr.ip().octets();
     let mut buf = [0_u8; 16];
     for i in 0..buf.len() {
-        if i < MAGIC_COOKIE.len() {
-            buf[i] = src_buf[i] ^ MAGIC_COOKIE[i];
+        if i < MAGIC_COOKIE_LEN {
+            buf[i] = src_buf[i] ^ magic_cookie[i];
         } else {
-            buf[i] = src_buf[i] ^ trans_id[i - MAGIC_COOKIE.len()];
+            buf[i] = src_buf[i] ^ trans_id[i - MAGIC_COOKIE_LEN];
         }
     }
 
     SocketAddrV6::new(Ipv6Addr::from(buf), port, 0, 0)
 }
 
-pub fn xor_address(addr: SocketAddr, trans_id: &TransId) -> SocketAddr {
+pub fn xor_address(
+    addr: SocketAddr,
+    magic_cookie: &[u8; MAGIC_COOKIE_LEN],
+    trans_id: &TransId,
+) -> SocketAddr {
     match addr {
-        SocketAddr::V4(v) => SocketAddr::V4(xor_address_v4(v)),
-        SocketAddr::V6(v) => SocketAddr::V6(xor_address_v6(v, trans_id)),
+        SocketAddr::V4(v) => SocketAddr::V4(xor_address_v4(v, magic_cookie)),
+        SocketAddr::V6(v) => SocketAddr::V6(xor_address_v6(v, magic_cookie, trans_id)),
     }
 }
 
@@ -98,7 +147,7 @@ pub fn pack_error_code(code: u16) -> u16 {
 
 pub fn unpack_error_code(code: u16) -> u16 {
     let n2 = code & 0x00ff;
-    let n1 = code << 8 & 0xff;
+    let n1 = code >> 8 & 0x07;
     n1 * 100 + n2
 }
 
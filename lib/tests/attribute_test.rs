@@ -0,0 +1,165 @@
+use bytes::Bytes;
+use std::net::SocketAddr;
+use stun_rs::attrs::address_attr::AddressAttr;
+use stun_rs::attrs::attribute::Attribute;
+use stun_rs::attrs::change_request::ChangeRequest;
+use stun_rs::attrs::errcode_attr::ErrcodeAttr;
+use stun_rs::attrs::nonce::Nonce;
+use stun_rs::attrs::realm::Realm;
+use stun_rs::attrs::software::Software;
+use stun_rs::attrs::username::Username;
+use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::attrs::RawAttr;
+use stun_rs::constants::*;
+use stun_rs::error::AttrValidator;
+use stun_rs::header::Header;
+use stun_rs::packet::Packet;
+use stun_rs::util;
+
+fn new_response() -> Packet {
+    let trans_id = util::new_trans_id();
+    let header = Header::new(MESSAGE_TYPE_BIND_RES, 0, trans_id);
+
+    let mapped: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+    let origin: SocketAddr = "10.0.0.1:3478".parse().unwrap();
+
+    let attrs = vec![
+        AddressAttr::new(ATTR_MAPPED_ADDRESS, mapped).into(),
+        XorMappedAddress::new(trans_id, mapped).into(),
+        AddressAttr::new(ATTR_RESPONSE_ORIGIN, origin).into(),
+        RawAttr::new(0x7f01, Bytes::from_static(&[1, 2, 3])),
+    ];
+
+    Packet::new(header, attrs)
+}
+
+#[test]
+pub fn test_typed_get() {
+    let buf = new_response().pack().unwrap();
+    let packet = Packet::unpack(buf).unwrap();
+
+    let xor = packet.get::<XorMappedAddress>().unwrap().unwrap();
+    assert_eq!(xor.address, "1.2.3.4:5678".parse().unwrap());
+
+    // 第一个地址类 attribute
+    let addr = packet.get::<AddressAttr>().unwrap().unwrap();
+    assert_eq!(addr.attr_type, ATTR_MAPPED_ADDRESS);
+
+    let origin = packet
+        .get_by_type::<AddressAttr>(ATTR_RESPONSE_ORIGIN)
+        .unwrap()
+        .unwrap();
+    assert_eq!(origin.address, "10.0.0.1:3478".parse().unwrap());
+
+    assert!(packet.get::<Username>().unwrap().is_none());
+    assert!(packet
+        .get_by_type::<AddressAttr>(ATTR_USERNAME)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+pub fn test_attributes_unknown_passthrough() {
+    let packet = Packet::unpack(new_response().pack().unwrap()).unwrap();
+
+    let attrs: Vec<Attribute> = packet.attributes().map(|x| x.unwrap()).collect();
+    assert_eq!(attrs.len(), 4);
+    assert!(matches!(attrs[0], Attribute::MappedAddress(_)));
+    assert!(matches!(attrs[1], Attribute::XorMappedAddress(_)));
+    assert!(matches!(attrs[2], Attribute::ResponseOrigin(_)));
+
+    let unknown = match &attrs[3] {
+        Attribute::Unknown(v) => v.clone(),
+        v => panic!("not unknown: {:?}", v),
+    };
+    assert_eq!(unknown.attr_type, 0x7f01);
+    assert_eq!(unknown.value.as_ref(), &[1, 2, 3]);
+
+    // 重新打包后和原来一样
+    let raw: Vec<RawAttr> = attrs.into_iter().map(|x| x.into()).collect();
+    let repacked = Packet::new(packet.header.clone(), raw);
+    assert_eq!(repacked.pack().unwrap(), packet.pack().unwrap());
+}
+
+#[test]
+pub fn test_change_request_decode() {
+    let trans_id = util::new_trans_id();
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, trans_id);
+    let packet = Packet::new(header, vec![ChangeRequest::new(true, false).into()]);
+
+    let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+    let attr = packet.get::<ChangeRequest>().unwrap().unwrap();
+    assert!(attr.change_ip);
+    assert!(!attr.change_port);
+}
+
+#[test]
+pub fn test_software() {
+    let header = Header::new(MESSAGE_TYPE_BIND_RES, 0, util::new_trans_id());
+    let packet = Packet::new(header, vec![Software::new("server 0.1.0").into()]);
+
+    let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+    assert!(packet.validate().is_none());
+    assert!(packet.unknown_attrs().is_empty());
+
+    let software = packet.get::<Software>().unwrap().unwrap();
+    assert_eq!(software.software, "server 0.1.0");
+
+    match packet.attributes().next() {
+        Some(Ok(Attribute::Software(v))) => assert_eq!(v.software, "server 0.1.0"),
+        v => panic!("{:?}", v),
+    }
+
+    assert!(Software::new(&"a".repeat(127)).validate().is_none());
+    assert!(Software::new(&"a".repeat(128)).validate().is_some());
+    assert!(Software::new(&"中".repeat(127)).validate().is_none());
+    assert!(Software::new(&"中".repeat(255)).validate().is_some());
+}
+
+#[test]
+pub fn test_realm_nonce() {
+    let realm: RawAttr = Realm::new("example.org").into();
+    let realm = Realm::try_from(realm).unwrap();
+    assert_eq!(realm.realm, "example.org");
+    assert!(realm.validate().is_none());
+
+    assert!(Realm::new(&"a".repeat(128)).validate().is_some());
+    assert!(Nonce::new(&"中".repeat(127)).validate().is_none());
+    assert!(Nonce::new(&"中".repeat(255)).validate().is_some());
+
+    let raw = RawAttr::new(ATTR_NONCE, Bytes::from_static(&[0xff, 0xfe]));
+    assert!(Nonce::try_from(raw).is_err());
+}
+
+#[test]
+pub fn test_alternate_server() {
+    let header = Header::new(MESSAGE_TYPE_BIND_ERR_RES, 0, util::new_trans_id());
+    let alternate: SocketAddr = "10.0.0.2:3478".parse().unwrap();
+    let packet = Packet::new(
+        header,
+        vec![
+            ErrcodeAttr::new(ERROR_CODE_TRY_ALTERNATE, "try alternate").into(),
+            AddressAttr::new(ATTR_ALTERNATE_SERVER, alternate).into(),
+        ],
+    );
+
+    let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+    assert!(packet.validate().is_none());
+    assert!(packet.unknown_attrs().is_empty());
+
+    let errcode = packet.get::<ErrcodeAttr>().unwrap().unwrap();
+    assert_eq!(errcode.code, ERROR_CODE_TRY_ALTERNATE);
+    assert_eq!(errcode.msg, "try alternate");
+
+    let addr = packet
+        .get_by_type::<AddressAttr>(ATTR_ALTERNATE_SERVER)
+        .unwrap()
+        .unwrap();
+    assert_eq!(addr.address, alternate);
+
+    let attr = packet.attributes().nth(1);
+    match attr {
+        Some(Ok(Attribute::AlternateServer(v))) => assert_eq!(v.address, alternate),
+        v => panic!("{:?}", v),
+    }
+}
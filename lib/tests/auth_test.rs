@@ -0,0 +1,168 @@
+use bytes::Bytes;
+use stun_rs::attrs::message_integrity_sha256::MessageIntegritySha256;
+use stun_rs::attrs::nonce::Nonce;
+use stun_rs::attrs::password_algorithm::{PasswordAlgorithm, PasswordAlgorithms};
+use stun_rs::attrs::realm::Realm;
+use stun_rs::attrs::userhash::Userhash;
+use stun_rs::attrs::username::Username;
+use stun_rs::attrs::RawAttr;
+use stun_rs::auth;
+use stun_rs::constants::*;
+use stun_rs::error::AttrValidator;
+use stun_rs::header::Header;
+use stun_rs::packet::Packet;
+use stun_rs::util;
+
+#[test]
+pub fn test_long_term_key() {
+    let key = auth::long_term_key("user", "realm", "pass");
+    let expected = [
+        0x84, 0x93, 0xfb, 0xc5, 0x3b, 0xa5, 0x82, 0xfb, 0x4c, 0x04, 0x4c, 0x45, 0x6b, 0xdc, 0x40,
+        0xeb,
+    ];
+    assert_eq!(key, expected);
+}
+
+#[test]
+pub fn test_credential_attrs() {
+    let raw: RawAttr = Username::new("alice").into();
+    assert_eq!(raw.attr_type, ATTR_USERNAME);
+    let username: Username = raw.try_into().unwrap();
+    assert_eq!(username.username, "alice");
+
+    let raw: RawAttr = Realm::new("example.org").into();
+    assert_eq!(raw.attr_type, ATTR_REALM);
+    let realm: Realm = raw.try_into().unwrap();
+    assert_eq!(realm.realm, "example.org");
+
+    let raw: RawAttr = Nonce::new("f//499k954d6OL34oL9FSTvy64sA").into();
+    assert_eq!(raw.attr_type, ATTR_NONCE);
+    let nonce: Nonce = raw.try_into().unwrap();
+    assert_eq!(nonce.nonce, "f//499k954d6OL34oL9FSTvy64sA");
+
+    assert!(Username::new(&"a".repeat(513)).validate().is_some());
+    assert!(Realm::new(&"a".repeat(128)).validate().is_some());
+    assert!(Nonce::new(&"a".repeat(127)).validate().is_none());
+}
+
+#[test]
+pub fn test_long_term_integrity() {
+    let key = auth::long_term_key("alice", "example.org", "secret");
+
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+    let mut packet = Packet::new(
+        header,
+        vec![
+            Username::new("alice").into(),
+            Realm::new("example.org").into(),
+            Nonce::new("f//499k954d6OL34oL9FSTvy64sA").into(),
+        ],
+    );
+    packet.add_message_integrity(&key).unwrap();
+
+    let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+    assert!(packet.validate().is_none());
+    assert!(packet.verify_message_integrity(&key).is_none());
+
+    let wrong_key = auth::long_term_key("alice", "example.org", "wrong");
+    assert!(packet.verify_message_integrity(&wrong_key).is_some());
+}
+
+#[test]
+pub fn test_long_term_key_sha256() {
+    let key = auth::long_term_key_with(PASSWORD_ALGORITHM_SHA256, "user", "realm", "pass");
+    let expected = [
+        0x07, 0xe9, 0x34, 0x11, 0x7a, 0xbd, 0x40, 0x83, 0x6e, 0x7c, 0x63, 0x29, 0xb5, 0x47, 0x31,
+        0xb2, 0xb2, 0xd2, 0xa5, 0xf9, 0xa7, 0x1f, 0x54, 0x49, 0x22, 0xd7, 0x5e, 0x07, 0x30, 0xd8,
+        0x25, 0x1b,
+    ];
+    assert_eq!(key.unwrap(), expected);
+
+    let hash = auth::userhash("user", "realm");
+    assert_eq!(&hash[..4], &[0x6a, 0x30, 0x29, 0x11]);
+
+    assert!(auth::long_term_key_with(0x0003, "user", "realm", "pass").is_none());
+}
+
+#[test]
+pub fn test_nonce_cookie() {
+    let features = SECURITY_FEATURE_PASSWORD_ALGORITHMS | SECURITY_FEATURE_USERNAME_ANONYMITY;
+    let cookie = auth::nonce_cookie(features);
+    assert_eq!(cookie, "obMatJos2wAAA");
+
+    let nonce = format!("{}{}", cookie, "f//499k954d6OL34oL9FSTvy64sA");
+    assert_eq!(auth::parse_nonce_cookie(&nonce), Some(features));
+    assert_eq!(
+        auth::parse_nonce_cookie("f//499k954d6OL34oL9FSTvy64sA"),
+        None
+    );
+
+    // 第 13 个字节在多字节字符中间
+    assert_eq!(auth::parse_nonce_cookie("obMatJos2AAA\u{e9}"), None);
+}
+
+#[test]
+pub fn test_password_algorithms() {
+    let algorithms = PasswordAlgorithms::new(vec![
+        PasswordAlgorithm::new(PASSWORD_ALGORITHM_SHA256),
+        PasswordAlgorithm::new(PASSWORD_ALGORITHM_MD5),
+    ]);
+
+    let raw: RawAttr = algorithms.clone().into();
+    assert_eq!(raw.attr_type, ATTR_PASSWORD_ALGORITHMS);
+    assert_eq!(
+        &raw.value[..],
+        &[0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00]
+    );
+
+    let parsed: PasswordAlgorithms = raw.try_into().unwrap();
+    assert_eq!(parsed, algorithms);
+    assert!(parsed.contains(PASSWORD_ALGORITHM_MD5));
+
+    let raw: RawAttr = PasswordAlgorithm::new(PASSWORD_ALGORITHM_SHA256).into();
+    let parsed: PasswordAlgorithm = raw.try_into().unwrap();
+    assert!(parsed.validate().is_none());
+    assert!(PasswordAlgorithm::new(0x0003).validate().is_some());
+}
+
+#[test]
+pub fn test_message_integrity_sha256() {
+    let key = auth::long_term_key_sha256("alice", "example.org", "secret");
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+    let mut packet = Packet::new(
+        header,
+        vec![
+            Userhash::new(auth::userhash("alice", "example.org")).into(),
+            Realm::new("example.org").into(),
+            Nonce::new("obMatJos2wAAAf//499k954d6OL34").into(),
+            PasswordAlgorithm::new(PASSWORD_ALGORITHM_SHA256).into(),
+        ],
+    );
+    packet.add_message_integrity(&key).unwrap();
+    packet.add_message_integrity_sha256(&key).unwrap();
+
+    let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+    assert!(packet.validate().is_none());
+    assert!(packet.verify_message_integrity(&key).is_none());
+    assert!(packet.verify_message_integrity_sha256(&key).is_none());
+
+    let wrong_key = auth::long_term_key_sha256("alice", "example.org", "wrong");
+    assert!(packet.verify_message_integrity_sha256(&wrong_key).is_some());
+}
+
+#[test]
+pub fn test_truncated_message_integrity_sha256() {
+    let key = auth::short_term_key("secret");
+    let mut header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+
+    // 截断到 16 字节, msg_len 只包括 4 + 16
+    header.msg_len = 4 + 16;
+    let hmac = auth::hmac_sha256(&key, &header.pack());
+
+    let mut packet = Packet::new(header, vec![]);
+    packet.add_attr(MessageIntegritySha256::new(Bytes::copy_from_slice(&hmac[..16])).into());
+
+    let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+    assert!(packet.validate().is_none());
+    assert!(packet.verify_message_integrity_sha256(&key).is_none());
+}
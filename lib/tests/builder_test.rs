@@ -0,0 +1,168 @@
+use rand::rngs::mock::StepRng;
+use std::net::SocketAddr;
+use stun_rs::attrs::address_attr::AddressAttr;
+use stun_rs::attrs::attribute::Attribute;
+use stun_rs::attrs::change_request::ChangeRequest;
+use stun_rs::attrs::errcode_attr::ErrcodeAttr;
+use stun_rs::attrs::nonce::Nonce;
+use stun_rs::attrs::password_algorithm::{PasswordAlgorithm, PasswordAlgorithms};
+use stun_rs::attrs::realm::Realm;
+use stun_rs::attrs::response_port::ResponsePort;
+use stun_rs::attrs::software::Software;
+use stun_rs::attrs::unknown_attributes::UnknownAttributes;
+use stun_rs::attrs::username::Username;
+use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::attrs::AttrPadding;
+use stun_rs::auth;
+use stun_rs::builder::MessageBuilder;
+use stun_rs::constants::*;
+use stun_rs::header::Header;
+use stun_rs::packet::{PackOptions, Packet};
+use stun_rs::util;
+
+#[test]
+pub fn test_builder_same_as_packet() {
+    let trans_id = util::new_trans_id();
+    let key = auth::short_term_key("pass");
+
+    let buf = MessageBuilder::request(METHOD_BINDING)
+        .unwrap()
+        .trans_id(trans_id)
+        .attr(Username::new("user"))
+        .attr(ChangeRequest::new(true, true))
+        .message_integrity(&key)
+        .message_integrity_sha256(&key)
+        .fingerprint()
+        .build()
+        .unwrap();
+
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, trans_id);
+    let mut packet = Packet::new(
+        header,
+        vec![
+            Username::new("user").into(),
+            ChangeRequest::new(true, true).into(),
+        ],
+    );
+    packet.add_message_integrity(&key).unwrap();
+    packet.add_message_integrity_sha256(&key).unwrap();
+    packet.add_fingerprint().unwrap();
+
+    assert_eq!(buf, packet.pack().unwrap());
+
+    let packet = Packet::unpack(buf).unwrap();
+    assert!(packet.validate().is_none());
+    assert!(packet.verify_message_integrity(&key).is_none());
+    assert!(packet.verify_message_integrity_sha256(&key).is_none());
+}
+
+#[test]
+pub fn test_builder_ordering() {
+    let key = auth::short_term_key("pass");
+
+    // fingerprint 和 message-integrity 总是在最后
+    let builder = MessageBuilder::request(METHOD_BINDING)
+        .unwrap()
+        .fingerprint()
+        .message_integrity(&key)
+        .attr_opt(Some(ResponsePort::new(8000)))
+        .attr_opt(None::<ChangeRequest>);
+
+    let packet = builder.clone().build_packet().unwrap();
+    let buf = builder.build().unwrap();
+    assert_eq!(buf, packet.pack().unwrap());
+
+    let packet = Packet::unpack(buf).unwrap();
+    let types: Vec<u16> = packet
+        .attributes()
+        .map(|x| x.unwrap().attr_type())
+        .collect();
+    assert_eq!(
+        types,
+        vec![ATTR_RESPONSE_PORT, ATTR_MESSAGE_INTEGRITY, ATTR_FINGERPRINT]
+    );
+    assert!(matches!(
+        packet.attributes().last(),
+        Some(Ok(Attribute::Fingerprint(_)))
+    ));
+    assert!(packet.validate().is_none());
+}
+
+// 每种 attribute 直接编码, 和经过 RawAttr 的结果一样
+// fingerprint 等由 build 计算, .attr(Fingerprint::new(0)) 编译不通过
+#[test]
+pub fn test_builder_typed_attrs() {
+    let trans_id = util::new_trans_id();
+    let addr: SocketAddr = "[1:2:3:4:5:6:7:8]:3478".parse().unwrap();
+
+    let builder = MessageBuilder::new(MESSAGE_TYPE_BIND_ERR_RES)
+        .trans_id(trans_id)
+        .attr(ErrcodeAttr::new(ERROR_CODE_UNKNOWN_ATTRIBUTE, "unknown"))
+        .attr(UnknownAttributes::new(vec![0x0002, 0x0030, 0x0031]))
+        .attr(Realm::new("example.org"))
+        .attr(Nonce::new("abc"))
+        .attr(PasswordAlgorithms::new(vec![
+            PasswordAlgorithm::new(PASSWORD_ALGORITHM_MD5),
+            PasswordAlgorithm::new(PASSWORD_ALGORITHM_SHA256),
+        ]))
+        .attr(AddressAttr::new(ATTR_ALTERNATE_SERVER, addr))
+        .attr(XorMappedAddress::new(trans_id, addr))
+        .attr(Software::new("builder"))
+        .fingerprint();
+
+    let buf = builder.clone().build().unwrap();
+    assert_eq!(buf.len(), builder.encoded_len());
+    assert_eq!(buf, builder.build_packet().unwrap().pack().unwrap());
+
+    let packet = Packet::unpack(buf).unwrap();
+    assert!(packet.validate().is_none());
+    assert_eq!(packet.get::<ErrcodeAttr>().unwrap().unwrap().msg, "unknown");
+    assert_eq!(
+        packet.get::<XorMappedAddress>().unwrap().unwrap().address,
+        addr
+    );
+}
+
+#[test]
+pub fn test_builder_reply_unpadded() {
+    let trans_id = util::new_classic_trans_id();
+    let req = Header::new_classic(MESSAGE_TYPE_BIND_REQ, 0, trans_id);
+
+    let builder = MessageBuilder::reply(&req, MESSAGE_TYPE_BIND_ERR_RES)
+        .attr(Username::new("abc"))
+        .padding(AttrPadding::Unpadded)
+        .fingerprint();
+    assert!(builder.header().is_classic());
+    assert_eq!(builder.header().classic_trans_id(), trans_id);
+
+    let options = PackOptions {
+        fingerprint: true,
+        padding: AttrPadding::Unpadded,
+    };
+    let expected = builder
+        .clone()
+        .build_packet()
+        .unwrap()
+        .pack_with(&options)
+        .unwrap();
+    assert_eq!(builder.build().unwrap(), expected);
+}
+
+#[test]
+pub fn test_builder_with_rng() {
+    // 固定的随机数, 相当于设备自己的生成器
+    let mut rng = StepRng::new(0x0102_0304_0506_0708, 0);
+    let trans_id = util::new_trans_id_with(&mut rng);
+    assert_eq!(trans_id, [8, 7, 6, 5, 4, 3, 2, 1, 8, 7, 6, 5]);
+
+    let buf = MessageBuilder::request_with_rng(METHOD_BINDING, &mut rng)
+        .unwrap()
+        .build()
+        .unwrap();
+    let packet = Packet::unpack(buf).unwrap();
+    assert_eq!(packet.header.msg_type, MESSAGE_TYPE_BIND_REQ);
+    assert_eq!(packet.header.trans_id, trans_id);
+
+    let classic = util::new_classic_trans_id_with(&mut rng);
+    assert_eq!(classic[..8], classic[8..]);
+}
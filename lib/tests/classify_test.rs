@@ -0,0 +1,58 @@
+use bytes::{BufMut, BytesMut};
+use stun_rs::attrs::username::Username;
+use stun_rs::builder::MessageBuilder;
+use stun_rs::classify::{classify, classify_with_fingerprint, PacketKind};
+use stun_rs::constants::*;
+use stun_rs::util;
+
+#[test]
+pub fn test_classify_stun() {
+    let buf = MessageBuilder::new(MESSAGE_TYPE_BIND_REQ)
+        .attr(Username::new("abcd"))
+        .build()
+        .unwrap();
+    assert_eq!(classify(&buf), PacketKind::Stun);
+    assert_eq!(classify_with_fingerprint(&buf), PacketKind::Foreign);
+
+    let buf = MessageBuilder::new(MESSAGE_TYPE_BIND_REQ)
+        .attr(Username::new("abcd"))
+        .fingerprint()
+        .build()
+        .unwrap();
+    assert_eq!(classify_with_fingerprint(&buf), PacketKind::Stun);
+
+    let mut bad = BytesMut::from(buf.as_ref());
+    let last = bad.len() - 1;
+    bad[last] ^= 1;
+    assert_eq!(classify(&bad), PacketKind::Stun);
+    assert_eq!(classify_with_fingerprint(&bad), PacketKind::Foreign);
+
+    // rfc 3489 的包没有 magic cookie
+    let buf = MessageBuilder::new(MESSAGE_TYPE_BIND_REQ)
+        .classic_trans_id(util::new_classic_trans_id())
+        .build()
+        .unwrap();
+    assert_eq!(classify(&buf), PacketKind::Foreign);
+
+    // 长度不对
+    assert_eq!(classify(&buf[..HEADER_LEN - 1]), PacketKind::Foreign);
+}
+
+#[test]
+pub fn test_classify_other() {
+    // channel data, 0x4001, 5 字节数据 + 3 字节 padding
+    let mut buf = BytesMut::new();
+    buf.put_u16(0x4001);
+    buf.put_u16(5);
+    buf.put_slice(&[1, 2, 3, 4, 5]);
+    assert_eq!(classify(&buf), PacketKind::ChannelData);
+    buf.put_bytes(0, 3);
+    assert_eq!(classify(&buf), PacketKind::ChannelData);
+    buf.put_u8(0);
+    assert_eq!(classify(&buf), PacketKind::Foreign);
+
+    // dtls handshake, rtp
+    assert_eq!(classify(&[22, 0xfe, 0xfd, 0, 0]), PacketKind::Foreign);
+    assert_eq!(classify(&[0x80, 0x60, 0, 1]), PacketKind::Foreign);
+    assert_eq!(classify(&[]), PacketKind::Foreign);
+}
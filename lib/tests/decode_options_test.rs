@@ -0,0 +1,100 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use stun_rs::attrs::response_port::ResponsePort;
+use stun_rs::attrs::username::Username;
+use stun_rs::attrs::RawAttr;
+use stun_rs::builder::MessageBuilder;
+use stun_rs::constants::*;
+use stun_rs::error::ParsePacketErr;
+use stun_rs::header::Header;
+use stun_rs::packet::{DecodeOptions, Packet};
+use stun_rs::packet_ref::PacketRef;
+use stun_rs::util;
+
+#[test]
+pub fn test_decode_limits() {
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+    let attrs = (0..4)
+        .map(|i| RawAttr::new(0x8100 + i, Bytes::from_static(&[0; 4])))
+        .collect();
+    let buf = Packet::new(header, attrs).pack().unwrap();
+    assert!(Packet::unpack(buf.clone()).is_ok());
+
+    let options = DecodeOptions {
+        max_attrs: 3,
+        ..Default::default()
+    };
+    assert_eq!(
+        Packet::unpack_with(buf.clone(), &options).unwrap_err(),
+        ParsePacketErr::TooManyAttrs { max: 3 }
+    );
+
+    let options = DecodeOptions {
+        max_size: HEADER_LEN + 16,
+        ..Default::default()
+    };
+    assert_eq!(
+        PacketRef::parse_with(&buf, &options).unwrap_err(),
+        ParsePacketErr::TooLarge {
+            max: HEADER_LEN + 16,
+            actual: HEADER_LEN + 32,
+        }
+    );
+}
+
+#[test]
+pub fn test_decode_strict() {
+    // rfc 3489 的请求
+    let buf = MessageBuilder::new(MESSAGE_TYPE_BIND_REQ)
+        .classic_trans_id(util::new_classic_trans_id())
+        .attr(ResponsePort::new(8000))
+        .build()
+        .unwrap();
+
+    assert!(Packet::unpack(buf.clone()).is_ok());
+    assert!(Packet::unpack_with(buf.clone(), &DecodeOptions::lenient()).is_ok());
+    assert_eq!(
+        Packet::unpack_with(buf, &DecodeOptions::strict()).unwrap_err(),
+        ParsePacketErr::NoMagicCookie
+    );
+
+    // msg_len 不是 4 的倍数
+    let buf = MessageBuilder::new(MESSAGE_TYPE_BIND_REQ).build().unwrap();
+    let mut buf = BytesMut::from(buf.as_ref());
+    buf[3] = 2;
+    buf.put_u16(0);
+
+    assert_eq!(
+        PacketRef::parse_with(&buf, &DecodeOptions::strict()).unwrap_err(),
+        ParsePacketErr::MsgLenNotAligned(2)
+    );
+    assert_eq!(
+        PacketRef::parse(&buf).unwrap_err(),
+        ParsePacketErr::TrailingBytes {
+            offset: HEADER_LEN,
+            len: 2,
+        }
+    );
+}
+
+#[test]
+pub fn test_decode_lenient() {
+    // 最后有不到 4 字节的多余数据
+    let buf = MessageBuilder::new(MESSAGE_TYPE_BIND_REQ)
+        .classic_trans_id(util::new_classic_trans_id())
+        .attr(Username::new("abcd"))
+        .build()
+        .unwrap();
+    let mut buf = BytesMut::from(buf.as_ref());
+    buf[3] += 3;
+    buf.put_slice(&[1, 2, 3]);
+    let buf = buf.freeze();
+
+    assert!(Packet::unpack(buf.clone()).is_err());
+
+    let packet = Packet::unpack_with(buf, &DecodeOptions::lenient()).unwrap();
+    assert_eq!(packet.attrs.len(), 1);
+    assert!(packet.validate().is_none());
+
+    let username: Username = packet.attrs[0].clone().try_into().unwrap();
+    assert_eq!(username.username, "abcd");
+}
@@ -0,0 +1,76 @@
+use bytes::Bytes;
+use std::net::SocketAddr;
+use stun_rs::attrs::address_attr::AddressAttr;
+use stun_rs::attrs::attribute::Attribute;
+use stun_rs::attrs::errcode_attr::ErrcodeAttr;
+use stun_rs::attrs::software::Software;
+use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::attrs::RawAttr;
+use stun_rs::constants::*;
+use stun_rs::header::Header;
+use stun_rs::message_type::{MessageClass, MessageType};
+use stun_rs::packet::Packet;
+use stun_rs::packet_ref::PacketRef;
+use stun_rs::util;
+
+#[test]
+pub fn test_display_packet() {
+    let trans_id = [1_u8; TRANS_ID_LEN];
+    let mapped: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+    let header = Header::new(MESSAGE_TYPE_BIND_RES, 0, trans_id);
+    let packet = Packet::new(
+        header,
+        vec![
+            AddressAttr::new(ATTR_MAPPED_ADDRESS, mapped).into(),
+            XorMappedAddress::new(trans_id, mapped).into(),
+            Software::new("server 0.1.0").into(),
+            RawAttr::new(0x8100, Bytes::from_static(&[0xde, 0xad])),
+            RawAttr::new(ATTR_CHANGE_REQUEST, Bytes::from_static(&[0, 0])),
+        ],
+    );
+    let buf = packet.pack().unwrap();
+
+    let expected = "Binding Success Response (0x0101), length: 56
+    Magic Cookie: 2112a442
+    Transaction ID: 010101010101010101010101
+    Attributes:
+        MAPPED-ADDRESS (0x0001), 8 bytes: 1.2.3.4:5678
+        XOR-MAPPED-ADDRESS (0x0020), 8 bytes: 1.2.3.4:5678
+        SOFTWARE (0x8022), 12 bytes: \"server 0.1.0\"
+        UNKNOWN (0x8100), 2 bytes: dead
+        CHANGE-REQUEST (0x0003), 2 bytes: 0000 (error: attr 0x0003 value len 2, expected 4)";
+
+    let packet = Packet::unpack(buf.clone()).unwrap();
+    assert_eq!(packet.to_string(), expected);
+    assert_eq!(PacketRef::parse(&buf).unwrap().to_string(), expected);
+    assert_eq!(util::print_packet(&buf), expected);
+
+    // 不是 stun 包, 输出错误和 hex
+    assert_eq!(
+        util::print_packet(&[0xff; 4]),
+        "buf len 4 < 20\nFF FF FF FF "
+    );
+}
+
+#[test]
+pub fn test_display_attr() {
+    let mapped: SocketAddr = "[::1]:3478".parse().unwrap();
+    let attr = Attribute::AlternateServer(AddressAttr::new(ATTR_ALTERNATE_SERVER, mapped));
+    assert_eq!(attr.to_string(), "ALTERNATE-SERVER (0x8023): [::1]:3478");
+
+    let attr = Attribute::ErrorCode(ErrcodeAttr::new(420, "unknown attribute"));
+    assert_eq!(
+        attr.to_string(),
+        "ERROR-CODE (0x0009): 420 unknown attribute"
+    );
+
+    let msg_type = MessageType::new(0x0005, MessageClass::Indication).unwrap();
+    assert_eq!(msg_type.to_string(), "Method 0x005 Indication (0x0015)");
+
+    // rfc 3489, 没有 magic cookie
+    let header = Header::new_classic(MESSAGE_TYPE_BIND_REQ, 0, [2_u8; CLASSIC_TRANS_ID_LEN]);
+    assert_eq!(
+        header.to_string(),
+        "Binding Request (0x0001), length: 0\n    Transaction ID: 02020202020202020202020202020202 (rfc 3489)"
+    );
+}
@@ -0,0 +1,118 @@
+use std::net::SocketAddr;
+use stun_rs::attrs::address_attr::AddressAttr;
+use stun_rs::attrs::username::Username;
+use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::attrs::{AttrPadding, RawAttr};
+use stun_rs::auth;
+use stun_rs::builder::MessageBuilder;
+use stun_rs::constants::*;
+use stun_rs::error::ParsePacketErr;
+use stun_rs::header::Header;
+use stun_rs::packet::{PackOptions, Packet};
+use stun_rs::util;
+
+fn new_packet() -> Packet {
+    let trans_id = util::new_trans_id();
+    let addr: SocketAddr = "192.0.2.1:32853".parse().unwrap();
+
+    MessageBuilder::new(MESSAGE_TYPE_BIND_RES)
+        .trans_id(trans_id)
+        .attr(XorMappedAddress::new(trans_id, addr))
+        .attr(Username::new("abc"))
+        .build_packet()
+        .unwrap()
+}
+
+#[test]
+pub fn test_encode_to_slice() {
+    let packet = new_packet();
+
+    for options in [
+        PackOptions::default(),
+        PackOptions {
+            fingerprint: true,
+            ..Default::default()
+        },
+        PackOptions {
+            fingerprint: true,
+            padding: AttrPadding::Unpadded,
+        },
+    ] {
+        let expected = packet.pack_with(&options).unwrap();
+        assert_eq!(packet.encoded_len(&options), expected.len());
+
+        let mut buf = [0_u8; 256];
+        let len = packet.encode_to_slice(&mut buf, &options).unwrap();
+        assert_eq!(&buf[..len], expected.as_ref());
+
+        let mut vec = Vec::new();
+        assert_eq!(packet.encode(&mut vec, &options).unwrap(), len);
+        assert_eq!(vec, expected.as_ref());
+
+        assert!(packet
+            .encode_to_slice(&mut buf[..len - 1], &options)
+            .is_err());
+    }
+
+    let buf = packet
+        .pack_with(&PackOptions {
+            fingerprint: true,
+            ..Default::default()
+        })
+        .unwrap();
+    assert!(Packet::unpack(buf).unwrap().validate().is_none());
+}
+
+#[test]
+pub fn test_encode_attr() {
+    let addr: SocketAddr = "[2001:db8::1]:3478".parse().unwrap();
+    let attr = AddressAttr::new(ATTR_MAPPED_ADDRESS, addr);
+
+    let mut buf = [0_u8; 24];
+    let len = attr.encode(&mut &mut buf[..]);
+    assert_eq!(len, 24);
+
+    let raw: RawAttr = attr.into();
+    assert_eq!(&buf[..], raw.pack().as_ref());
+
+    let mut buf = [0xff_u8; 8];
+    let raw = RawAttr::new(ATTR_USERNAME, bytes::Bytes::from_static(b"ab"));
+    assert_eq!(raw.encode(&mut &mut buf[..], AttrPadding::Padded), 8);
+    assert_eq!(buf, [0, 6, 0, 2, b'a', b'b', 0, 0]);
+}
+
+#[test]
+pub fn test_build_into() {
+    let key = auth::short_term_key("pass");
+    let builder = MessageBuilder::request(METHOD_BINDING)
+        .unwrap()
+        .attr(Username::new("user"))
+        .message_integrity(&key)
+        .fingerprint();
+
+    let mut buf = [0_u8; 128];
+    let len = builder.build_into(&mut buf).unwrap();
+    assert_eq!(len, builder.encoded_len());
+    assert!(builder.build_into(&mut buf[..len - 1]).is_err());
+
+    let expected = builder.build().unwrap();
+    assert_eq!(&buf[..len], expected.as_ref());
+}
+
+#[test]
+pub fn test_encode_too_large() {
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+    let value = bytes::Bytes::from(vec![0_u8; 0xFFFF]);
+    let packet = Packet::new(header, vec![RawAttr::new(0x8050, value)]);
+
+    assert!(matches!(
+        packet.pack(),
+        Err(ParsePacketErr::TooLarge { .. })
+    ));
+
+    let mut buf = [0_u8; 16];
+    assert!(matches!(
+        packet.encode(&mut &mut buf[..], &PackOptions::default()),
+        Err(ParsePacketErr::TooLarge { .. })
+    ));
+}
@@ -0,0 +1,148 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use std::error::Error as _;
+use std::net::SocketAddr;
+use stun_rs::attrs::address_attr::AddressAttr;
+use stun_rs::attrs::errcode_attr::ErrcodeAttr;
+use stun_rs::attrs::response_port::ResponsePort;
+use stun_rs::attrs::username::Username;
+use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::attrs::RawAttr;
+use stun_rs::builder::MessageBuilder;
+use stun_rs::constants::*;
+use stun_rs::error::{AttrValidator, Error, ParsePacketErr, ValidateErr};
+use stun_rs::header::Header;
+use stun_rs::packet::Packet;
+use stun_rs::util;
+
+#[test]
+pub fn test_parse_err_offset() {
+    // 第二个 attribute 的长度超出了包
+    let buf = MessageBuilder::new(MESSAGE_TYPE_BIND_REQ)
+        .attr(Username::new("abcd"))
+        .attr(ResponsePort::new(8000))
+        .build()
+        .unwrap();
+    let mut buf = BytesMut::from(buf.as_ref());
+    buf[HEADER_LEN + 8 + 3] = 8;
+
+    let err = Packet::unpack(buf.freeze()).unwrap_err();
+    assert_eq!(
+        err,
+        ParsePacketErr::AttrTruncated {
+            attr_type: ATTR_RESPONSE_PORT,
+            offset: HEADER_LEN + 8,
+            expected: 12,
+            actual: 8,
+        }
+    );
+    assert_eq!(err.attr_type(), Some(ATTR_RESPONSE_PORT));
+    assert_eq!(err.error_code(), Some(ERROR_CODE_BAD_REQUEST));
+    assert_eq!(
+        err.to_string(),
+        "attr 0x0027 at offset 28 truncated: need 12 bytes, 8 left"
+    );
+
+    // 不是 stun 包, 不回复
+    let err = Packet::unpack(Bytes::from_static(&[0_u8; 8])).unwrap_err();
+    assert_eq!(
+        err,
+        ParsePacketErr::BufSize {
+            expected: HEADER_LEN,
+            actual: 8
+        }
+    );
+    assert_eq!(err.error_code(), None);
+}
+
+#[test]
+pub fn test_attr_err() {
+    let header = Header::new(MESSAGE_TYPE_BIND_RES, 0, util::new_trans_id());
+    let addr: SocketAddr = "127.0.0.1:3478".parse().unwrap();
+
+    // 不是 xor-mapped-address
+    let raw: RawAttr = AddressAttr::new(ATTR_MAPPED_ADDRESS, addr).into();
+    let err = XorMappedAddress::from_base_attr(raw, &header).unwrap_err();
+    assert_eq!(
+        err,
+        ParsePacketErr::WrongAttrType {
+            expected: ATTR_XOR_MAPPED_ADDRESS,
+            actual: ATTR_MAPPED_ADDRESS,
+        }
+    );
+
+    let mut value = BytesMut::new();
+    value.put_u16(0x0003);
+    value.put_u16(3478);
+    let raw = RawAttr::new(ATTR_MAPPED_ADDRESS, value.freeze());
+    let err = AddressAttr::try_from(raw).unwrap_err();
+    assert_eq!(
+        err,
+        ParsePacketErr::BadFamily {
+            attr_type: ATTR_MAPPED_ADDRESS,
+            family: 3
+        }
+    );
+
+    // number 只能是 0-99
+    let mut value = BytesMut::new();
+    value.put_u16(0);
+    value.put_u16(0x04fc);
+    let raw = RawAttr::new(ATTR_ERROR_CODE, value.freeze());
+    let err = ErrcodeAttr::try_from(raw).unwrap_err();
+    assert_eq!(
+        err,
+        ParsePacketErr::BadAttrValue {
+            attr_type: ATTR_ERROR_CODE,
+            reason: "error code number > 99",
+        }
+    );
+
+    // attr_len + 4 超过 u16
+    let err = RawAttr::unpack(Bytes::from_static(&[0x80, 0x22, 0xff, 0xff, 0x00])).unwrap_err();
+    assert_eq!(
+        err,
+        ParsePacketErr::AttrTruncated {
+            attr_type: ATTR_SOFTWARE,
+            offset: 0,
+            expected: 0xffff + 4,
+            actual: 5,
+        }
+    );
+
+    let err = Username::new(&"a".repeat(USERNAME_MAX_LEN + 1))
+        .validate()
+        .unwrap();
+    assert_eq!(
+        err,
+        ValidateErr::TooLong {
+            attr_type: ATTR_USERNAME,
+            len: USERNAME_MAX_LEN + 1,
+            max: USERNAME_MAX_LEN,
+        }
+    );
+}
+
+#[test]
+pub fn test_error_code() {
+    let key = b"key";
+    let mut packet = Packet::new(
+        Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id()),
+        vec![],
+    );
+    packet.add_message_integrity(key).unwrap();
+
+    let err = packet.verify_message_integrity(b"other").unwrap();
+    assert_eq!(err, ValidateErr::IntegrityMismatch(ATTR_MESSAGE_INTEGRITY));
+    assert_eq!(err.error_code(), Some(ERROR_CODE_UNAUTHORIZED));
+
+    let err: Error = ValidateErr::from(ParsePacketErr::NotUtf8 {
+        attr_type: ATTR_REALM,
+    })
+    .into();
+    assert_eq!(err.error_code(), Some(ERROR_CODE_BAD_REQUEST));
+    assert_eq!(err.to_string(), "validate error: attr 0x0014 is not utf8");
+    assert!(err.source().unwrap().source().is_some());
+
+    let err = ValidateErr::FingerprintMismatch;
+    assert_eq!(err.error_code(), None);
+}
@@ -0,0 +1,82 @@
+use stun_rs::attrs::change_request::ChangeRequest;
+use stun_rs::attrs::fingerprint::Fingerprint;
+use stun_rs::attrs::RawAttr;
+use stun_rs::auth;
+use stun_rs::constants::*;
+use stun_rs::header::Header;
+use stun_rs::packet::{PackOptions, Packet};
+
+fn new_request() -> Packet {
+    let trans_id = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+    Packet::new(Header::new(MESSAGE_TYPE_BIND_REQ, 0, trans_id), vec![])
+}
+
+#[test]
+pub fn test_add_fingerprint() {
+    let mut packet = new_request();
+    packet.add_fingerprint().unwrap();
+
+    assert_eq!(packet.header.msg_len, 8);
+    let attr: Fingerprint = packet.attrs[0].clone().try_into().unwrap();
+    assert_eq!(attr.crc, 0x5b0ff6fc);
+    assert!(packet.verify_fingerprint().is_none());
+}
+
+#[test]
+pub fn test_pack_with_fingerprint() {
+    let mut packet = new_request();
+    packet.add_attr(ChangeRequest::new(false, false).into());
+    packet
+        .add_message_integrity(&auth::short_term_key("secret"))
+        .unwrap();
+
+    let buf = packet
+        .pack_with(&PackOptions {
+            fingerprint: true,
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(buf.len(), packet.pack().unwrap().len() + 8);
+
+    let packet = Packet::unpack(buf).unwrap();
+    assert!(packet.validate().is_none());
+    assert_eq!(packet.attrs.last().unwrap().attr_type, ATTR_FINGERPRINT);
+    assert!(packet
+        .verify_message_integrity(&auth::short_term_key("secret"))
+        .is_none());
+
+    // 再次添加 fingerprint, 会替换原来的
+    let buf2 = packet
+        .pack_with(&PackOptions {
+            fingerprint: true,
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(buf2.len(), packet.pack().unwrap().len());
+}
+
+#[test]
+pub fn test_unpack_bad_fingerprint() {
+    let buf = new_request()
+        .pack_with(&PackOptions {
+            fingerprint: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+    let mut bad = buf.to_vec();
+    let len = bad.len();
+    bad[len - 1] ^= 0xff;
+    assert!(Packet::unpack(bad.into()).is_err());
+
+    // fingerprint 不是最后一个
+    let mut packet = new_request();
+    packet.add_fingerprint().unwrap();
+    packet.add_attr(ChangeRequest::new(false, true).into());
+    assert!(packet.validate().is_some());
+    assert!(Packet::unpack(packet.pack().unwrap()).is_err());
+
+    let raw = RawAttr::new(ATTR_FINGERPRINT, vec![0, 0].into());
+    let attr: Result<Fingerprint, _> = raw.try_into();
+    assert!(attr.is_err());
+}
@@ -0,0 +1,75 @@
+use std::net::SocketAddr;
+use stun_rs::attrs::message_integrity::MessageIntegrity;
+use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::auth;
+use stun_rs::constants::*;
+use stun_rs::header::Header;
+use stun_rs::packet::Packet;
+use stun_rs::util;
+
+const PASSWORD: &str = "VOkJxbRl1RmTxUk/WvJxBt";
+
+fn new_response() -> Packet {
+    let trans_id = [
+        0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34, 0xd6, 0x86, 0xfa, 0x87, 0xdf, 0xae,
+    ];
+    let header = Header::new(MESSAGE_TYPE_BIND_RES, 0, trans_id);
+    let mapped_addr: SocketAddr = "192.0.2.1:32853".parse().unwrap();
+
+    Packet::new(
+        header,
+        vec![XorMappedAddress::new(trans_id, mapped_addr).into()],
+    )
+}
+
+#[test]
+pub fn test_add_message_integrity() {
+    let mut packet = new_response();
+    packet
+        .add_message_integrity(&auth::short_term_key(PASSWORD))
+        .unwrap();
+
+    assert_eq!(packet.header.msg_len, 12 + 24);
+
+    let attr: MessageIntegrity = packet.attrs[1].clone().try_into().unwrap();
+    let expected = [
+        0x74, 0xc9, 0x37, 0x1e, 0xbf, 0x31, 0x48, 0x54, 0x85, 0x18, 0x69, 0x9c, 0x3e, 0x31, 0x74,
+        0xc2, 0x0d, 0xd9, 0xe6, 0x8a,
+    ];
+    assert_eq!(attr.hmac, expected);
+}
+
+#[test]
+pub fn test_verify_message_integrity() {
+    let key = auth::short_term_key(PASSWORD);
+
+    let mut packet = new_response();
+    packet.add_message_integrity(&key).unwrap();
+
+    let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+    assert!(packet.validate().is_none());
+    assert!(packet.verify_message_integrity(&key).is_none());
+    assert!(packet
+        .verify_message_integrity(&auth::short_term_key("wrong"))
+        .is_some());
+}
+
+#[test]
+pub fn test_verify_tampered_packet() {
+    let key = auth::short_term_key(PASSWORD);
+
+    let mut packet = new_response();
+    packet.add_message_integrity(&key).unwrap();
+
+    let mut buf = packet.pack().unwrap().to_vec();
+    // 修改 xor-mapped-address 的端口
+    buf[HEADER_LEN + 6] ^= 0x01;
+    let packet = Packet::unpack(buf.into()).unwrap();
+    assert!(packet.verify_message_integrity(&key).is_some());
+
+    let packet = Packet::new(
+        Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id()),
+        vec![],
+    );
+    assert!(packet.verify_message_integrity(&key).is_some());
+}
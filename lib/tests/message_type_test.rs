@@ -0,0 +1,68 @@
+use stun_rs::constants::*;
+use stun_rs::error::ParsePacketErr;
+use stun_rs::header::Header;
+use stun_rs::message_type::{MessageClass, MessageType};
+use stun_rs::util;
+
+#[test]
+pub fn test_binding_message_type() {
+    assert_eq!(MESSAGE_TYPE_BIND_REQ.to_u16(), 0x0001);
+    assert_eq!(MESSAGE_TYPE_BIND_IND.to_u16(), 0x0011);
+    assert_eq!(MESSAGE_TYPE_BIND_RES.to_u16(), 0x0101);
+    assert_eq!(MESSAGE_TYPE_BIND_ERR_RES.to_u16(), 0x0111);
+
+    let msg_type = MessageType::from_u16(0x0111).unwrap();
+    assert_eq!(msg_type.method(), METHOD_BINDING);
+    assert_eq!(msg_type.class, MessageClass::ErrorResponse);
+}
+
+#[test]
+pub fn test_message_type_round_trip() {
+    let classes = [
+        MessageClass::Request,
+        MessageClass::Indication,
+        MessageClass::SuccessResponse,
+        MessageClass::ErrorResponse,
+    ];
+
+    for method in 0..=MESSAGE_METHOD_MASK {
+        for class in classes {
+            let msg_type = MessageType::new(method, class).unwrap();
+            let value = msg_type.to_u16();
+            assert_eq!(value & 0xc000, 0);
+            assert_eq!(MessageType::from_u16(value).unwrap(), msg_type);
+        }
+    }
+}
+
+#[test]
+pub fn test_turn_message_type() {
+    // allocate error response, rfc 8656
+    let msg_type = MessageType::new(METHOD_ALLOCATE, MessageClass::ErrorResponse).unwrap();
+    assert_eq!(msg_type.to_u16(), 0x0113);
+
+    // send indication
+    let msg_type = MessageType::new(METHOD_SEND, MessageClass::Indication).unwrap();
+    assert_eq!(msg_type.to_u16(), 0x0016);
+
+    let msg_type = MessageType::new(0x0fff, MessageClass::Request).unwrap();
+    assert_eq!(msg_type.to_u16(), 0x3eef);
+}
+
+#[test]
+pub fn test_bad_message_type() {
+    assert!(MessageType::from_u16(0x8001).is_err());
+    assert!(MessageType::from_u16(0x4001).is_err());
+
+    let mut buf = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id())
+        .pack()
+        .to_vec();
+    buf[0] = 0xc0;
+    assert!(Header::unpack(buf.into()).is_err());
+
+    // method 只有 12 bit
+    assert_eq!(
+        MessageType::new(0x1000, MessageClass::Request),
+        Err(ParsePacketErr::BadMethod(0x1000))
+    );
+}
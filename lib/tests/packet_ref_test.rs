@@ -0,0 +1,107 @@
+use bytes::Bytes;
+use std::net::SocketAddr;
+use stun_rs::attrs::address_attr::AddressAttr;
+use stun_rs::attrs::attribute::Attribute;
+use stun_rs::attrs::change_request::ChangeRequest;
+use stun_rs::attrs::username::Username;
+use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::attrs::{AttrPadding, RawAttr};
+use stun_rs::builder::MessageBuilder;
+use stun_rs::constants::*;
+use stun_rs::header::Header;
+use stun_rs::packet::{DecodeOptions, PackOptions, Packet};
+use stun_rs::packet_ref::PacketRef;
+use stun_rs::util;
+
+#[test]
+pub fn test_packet_ref_get() {
+    let trans_id = util::new_trans_id();
+    let addr: SocketAddr = "[2001:db8::1]:40000".parse().unwrap();
+
+    let buf = MessageBuilder::new(MESSAGE_TYPE_BIND_RES)
+        .trans_id(trans_id)
+        .attr(AddressAttr::new(ATTR_MAPPED_ADDRESS, addr))
+        .attr(XorMappedAddress::new(trans_id, addr))
+        .attr(Username::new("abcde"))
+        .fingerprint()
+        .build()
+        .unwrap();
+
+    let packet = PacketRef::parse(&buf).unwrap();
+    assert!(packet.validate().is_none());
+    assert!(!packet.has_unknown_attrs());
+    assert_eq!(packet.attrs().count(), 4);
+
+    let xor = packet.get::<XorMappedAddress>().unwrap().unwrap();
+    assert_eq!(xor.address, addr);
+
+    let mapped = packet
+        .get_by_type::<AddressAttr>(ATTR_MAPPED_ADDRESS)
+        .unwrap()
+        .unwrap();
+    assert_eq!(mapped.address, addr);
+
+    let username = packet.get::<Username>().unwrap().unwrap();
+    assert_eq!(username.username, "abcde");
+
+    // 借用的 value 不包括 padding
+    let raw = packet.attrs().nth(2).unwrap();
+    assert_eq!(raw.value, b"abcde");
+
+    assert!(matches!(
+        packet.attributes().last(),
+        Some(Ok(Attribute::Fingerprint(_)))
+    ));
+
+    // 和 Packet 的结果一样
+    let owned = Packet::unpack(buf.clone()).unwrap();
+    assert_eq!(packet.to_packet().pack().unwrap(), owned.pack().unwrap());
+    assert_eq!(owned.pack().unwrap(), buf);
+}
+
+#[test]
+pub fn test_packet_ref_unpadded() {
+    let packet = MessageBuilder::new(MESSAGE_TYPE_BIND_REQ)
+        .attr(Username::new("abc"))
+        .attr(ChangeRequest::new(false, true))
+        .build_packet()
+        .unwrap();
+
+    let options = PackOptions {
+        padding: AttrPadding::Unpadded,
+        ..Default::default()
+    };
+    let buf = packet.pack_with(&options).unwrap();
+
+    assert!(PacketRef::parse(&buf).is_err());
+
+    let packet = PacketRef::parse_with(&buf, &DecodeOptions::lenient()).unwrap();
+    let change = packet.get::<ChangeRequest>().unwrap().unwrap();
+    assert!(!change.change_ip);
+    assert!(change.change_port);
+}
+
+#[test]
+pub fn test_packet_ref_bad() {
+    // MessageBuilder 只接受已知类型的 attribute
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+    let mut packet = Packet::new(
+        header,
+        vec![RawAttr::new(0x0002, Bytes::from_static(&[1, 2, 3, 4]))],
+    );
+    let buf = packet.pack().unwrap();
+    assert!(PacketRef::parse(&buf).unwrap().has_unknown_attrs());
+
+    // fingerprint 不是最后一个
+    packet.add_fingerprint().unwrap();
+    packet.add_attr(Username::new("a").into());
+    assert!(PacketRef::parse(&packet.pack().unwrap()).is_err());
+
+    // 长度不对
+    let buf = MessageBuilder::new(MESSAGE_TYPE_BIND_REQ)
+        .attr(Username::new("abcd"))
+        .build()
+        .unwrap();
+    assert!(PacketRef::parse(&buf[..buf.len() - 1]).is_err());
+    assert!(PacketRef::parse(&buf[..10]).is_err());
+}
@@ -4,12 +4,16 @@ use std::net::{Ipv6Addr, SocketAddr, SocketAddrV6};
 use stun_rs::attrs::address_attr::AddressAttr;
 use stun_rs::attrs::change_request::ChangeRequest;
 use stun_rs::attrs::errcode_attr::ErrcodeAttr;
+use stun_rs::attrs::nonce::Nonce;
 use stun_rs::attrs::response_port::ResponsePort;
+use stun_rs::attrs::username::Username;
 use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::attrs::{AttrPadding, RawAttr};
 
 use stun_rs::constants::*;
+use stun_rs::error::ParsePacketErr;
 use stun_rs::header::Header;
-use stun_rs::packet::Packet;
+use stun_rs::packet::{DecodeOptions, PackOptions, Packet};
 use stun_rs::util;
 
 #[test]
@@ -33,7 +37,7 @@ pub fn test_new_err_response_packet() {
     attr_list.push(ErrcodeAttr::new(502, "not auth").into());
 
     let packet = Packet::new(header, attr_list);
-    let buf = packet.pack();
+    let buf = packet.pack().unwrap();
 
     println!("{}", util::print_bytes(&buf, " ", 8));
 }
@@ -56,7 +60,7 @@ pub fn test_new_response_packet() {
     attr_list.push(XorMappedAddress::new(trans_id, mapped_addr).into());
 
     let packet = Packet::new(header, attr_list);
-    let buf = packet.pack();
+    let buf = packet.pack().unwrap();
 
     println!("{}", util::print_bytes(&buf, " ", 8));
 }
@@ -73,7 +77,7 @@ pub fn test_new_req_packet() {
     attr_list.push(util::new_padding_attr(16).into());
 
     let packet = Packet::new(header, attr_list);
-    let buf = packet.pack();
+    let buf = packet.pack().unwrap();
 
     println!("{}", util::print_bytes(&buf, " ", 8));
 }
@@ -90,7 +94,7 @@ pub fn test_unpack_req() {
     attr_list.push(util::new_padding_attr(16).into());
 
     let packet = Packet::new(header, attr_list);
-    let buf = packet.pack();
+    let buf = packet.pack().unwrap();
 
     println!("{}", util::print_bytes(&buf, " ", 8));
     println!("----------------------");
@@ -118,7 +122,7 @@ pub fn test_unpack_response() {
     attr_list.push(XorMappedAddress::new(trans_id, mapped_addr).into());
 
     let packet = Packet::new(header, attr_list);
-    let buf = packet.pack();
+    let buf = packet.pack().unwrap();
 
     println!("{}", util::print_bytes(&buf, " ", 8));
     println!("----------------------");
@@ -126,3 +130,111 @@ pub fn test_unpack_response() {
     assert!(packet.validate().is_none());
     println!("{:?}", packet);
 }
+
+#[test]
+pub fn test_unpack_classic_req() {
+    let trans_id = util::new_classic_trans_id();
+
+    let header = Header::new_classic(MESSAGE_TYPE_BIND_REQ, 0, trans_id);
+    let packet = Packet::new(header, vec![ChangeRequest::new(false, false).into()]);
+    let buf = packet.pack().unwrap();
+
+    let packet = Packet::unpack(buf).unwrap();
+    assert!(packet.validate().is_none());
+    assert!(packet.is_classic());
+    assert_eq!(packet.header.classic_trans_id(), trans_id);
+}
+
+#[test]
+pub fn test_unpack_modern_req() {
+    let trans_id = util::new_trans_id();
+
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, trans_id);
+    let buf = Packet::new(header, vec![]).pack().unwrap();
+    assert_eq!(&buf[4..8], &MAGIC_COOKIE);
+
+    let packet = Packet::unpack(buf).unwrap();
+    assert!(packet.header.is_modern());
+    assert_eq!(packet.header.trans_id, trans_id);
+}
+
+#[test]
+pub fn test_xor_address_classic() {
+    let mapped_addr: SocketAddr = "[1:2:3:4:5:6:7:8]:8080".parse().unwrap();
+
+    let header = Header::new(MESSAGE_TYPE_BIND_RES, 0, util::new_trans_id());
+    let attrs = vec![XorMappedAddress::from_header(&header, mapped_addr).into()];
+    let packet = Packet::unpack(Packet::new(header, attrs).pack().unwrap()).unwrap();
+
+    let xor = XorMappedAddress::from_base_attr(packet.attrs[0].clone(), &packet.header);
+    assert_eq!(xor.unwrap().address, mapped_addr);
+
+    // rfc 3489 的包没有 magic cookie, 不能解析 xor-mapped-address
+    let header = Header::new_classic(MESSAGE_TYPE_BIND_RES, 0, util::new_classic_trans_id());
+    let attrs = vec![XorMappedAddress::from_header(&header, mapped_addr).into()];
+    let packet = Packet::unpack(Packet::new(header, attrs).pack().unwrap()).unwrap();
+
+    let xor = XorMappedAddress::from_base_attr(packet.attrs[0].clone(), &packet.header);
+    assert_eq!(xor.unwrap_err(), ParsePacketErr::NoMagicCookie);
+    assert!(packet.validate().is_some());
+}
+
+#[test]
+pub fn test_attr_padding() {
+    let raw: RawAttr = Username::new("evtj:h6vY").into();
+    assert_eq!(raw.attr_len, 9);
+    assert_eq!(raw.padding_len(), 3);
+    assert_eq!(raw.len(), 16);
+    assert_eq!(raw.pack().len(), 16);
+    assert_eq!(raw.len_with(AttrPadding::Unpadded), 13);
+
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+    let packet = Packet::new(
+        header,
+        vec![
+            raw,
+            ResponsePort::new(8080).into(),
+            Nonce::new("abcde").into(),
+        ],
+    );
+    assert_eq!(packet.header.msg_len, 16 + 8 + 12);
+
+    let buf = packet.pack().unwrap();
+    assert_eq!(buf.len() % 4, 0);
+
+    let packet = Packet::unpack(buf).unwrap();
+    assert!(packet.validate().is_none());
+    assert_eq!(packet.attrs.len(), 3);
+
+    let username: Username = packet.attrs[0].clone().try_into().unwrap();
+    assert_eq!(username.username, "evtj:h6vY");
+    let nonce: Nonce = packet.attrs[2].clone().try_into().unwrap();
+    assert_eq!(nonce.nonce, "abcde");
+}
+
+#[test]
+pub fn test_unpadded_peer() {
+    let header = Header::new_classic(MESSAGE_TYPE_BIND_REQ, 0, util::new_classic_trans_id());
+    let packet = Packet::new(
+        header,
+        vec![Username::new("abc").into(), ResponsePort::new(8080).into()],
+    );
+
+    let options = PackOptions {
+        padding: AttrPadding::Unpadded,
+        ..Default::default()
+    };
+    let buf = packet.pack_with(&options).unwrap();
+    assert_eq!(buf.len(), HEADER_LEN + 7 + 8);
+
+    // 按照 rfc 5389 的方式解析会失败
+    assert!(Packet::unpack(buf.clone()).is_err());
+
+    let packet = Packet::unpack_with(buf, &DecodeOptions::lenient()).unwrap();
+    assert!(packet.validate().is_none());
+    assert_eq!(packet.attrs.len(), 2);
+    assert_eq!(packet.header.msg_len, 8 + 8);
+
+    let port: ResponsePort = packet.attrs[1].clone().try_into().unwrap();
+    assert_eq!(port.port, 8080);
+}
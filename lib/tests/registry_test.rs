@@ -0,0 +1,249 @@
+use bytes::{BufMut, Bytes};
+use std::fmt;
+use stun_rs::attrs::attribute::{Attribute, TypedAttr};
+use stun_rs::attrs::registry::{AttrRegistry, CustomAttr};
+use stun_rs::attrs::RawAttr;
+use stun_rs::constants::*;
+use stun_rs::error::{AttrValidator, ParsePacketErr, ValidateErr};
+use stun_rs::header::Header;
+use stun_rs::packet::Packet;
+use stun_rs::packet_ref::PacketRef;
+use stun_rs::util;
+
+const ATTR_VENDOR_LEVEL: u16 = 0xc001;
+
+// 私有 attribute: 4 字节的 level, 只允许 0..=3
+#[derive(Debug)]
+struct VendorLevel {
+    level: u32,
+}
+
+impl From<VendorLevel> for RawAttr {
+    fn from(attr: VendorLevel) -> Self {
+        RawAttr::from_typed(ATTR_VENDOR_LEVEL, &attr)
+    }
+}
+
+impl TypedAttr for VendorLevel {
+    fn is_type(attr_type: u16) -> bool {
+        attr_type == ATTR_VENDOR_LEVEL
+    }
+
+    fn value_len(&self) -> usize {
+        4
+    }
+
+    fn encode_value<B: BufMut>(&self, buf: &mut B) {
+        buf.put_u32(self.level);
+    }
+
+    fn decode(raw: &RawAttr, _header: &Header) -> Result<Self, ParsePacketErr> {
+        let value: [u8; 4] = match raw.value.as_ref().try_into() {
+            Ok(v) => v,
+            Err(_) => {
+                return Err(ParsePacketErr::AttrLen {
+                    attr_type: raw.attr_type,
+                    expected: 4,
+                    actual: raw.value.len(),
+                })
+            }
+        };
+
+        Ok(Self {
+            level: u32::from_be_bytes(value),
+        })
+    }
+}
+
+impl AttrValidator for VendorLevel {
+    fn validate(&self) -> Option<ValidateErr> {
+        if self.level <= 3 {
+            return None;
+        }
+        Some(ValidateErr::Other(format!("vendor level: {}", self.level)))
+    }
+}
+
+impl fmt::Display for VendorLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "level {}", self.level)
+    }
+}
+
+impl CustomAttr for VendorLevel {
+    const NAME: &'static str = "VENDOR-LEVEL";
+}
+
+// comprehension-required 的私有 attribute, 没有 value
+const ATTR_VENDOR_FLAG: u16 = 0x4001;
+
+#[derive(Debug)]
+struct VendorFlag;
+
+impl From<VendorFlag> for RawAttr {
+    fn from(attr: VendorFlag) -> Self {
+        RawAttr::from_typed(ATTR_VENDOR_FLAG, &attr)
+    }
+}
+
+impl TypedAttr for VendorFlag {
+    fn is_type(attr_type: u16) -> bool {
+        attr_type == ATTR_VENDOR_FLAG
+    }
+
+    fn value_len(&self) -> usize {
+        0
+    }
+
+    fn encode_value<B: BufMut>(&self, _buf: &mut B) {}
+
+    fn decode(raw: &RawAttr, _header: &Header) -> Result<Self, ParsePacketErr> {
+        match raw.value.is_empty() {
+            true => Ok(Self),
+            false => Err(ParsePacketErr::AttrLen {
+                attr_type: raw.attr_type,
+                expected: 0,
+                actual: raw.value.len(),
+            }),
+        }
+    }
+}
+
+impl AttrValidator for VendorFlag {
+    fn validate(&self) -> Option<ValidateErr> {
+        None
+    }
+}
+
+impl fmt::Display for VendorFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "set")
+    }
+}
+
+impl CustomAttr for VendorFlag {
+    const NAME: &'static str = "VENDOR-FLAG";
+}
+
+fn new_request(attr: RawAttr) -> Packet {
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+    Packet::new(header, vec![attr])
+}
+
+#[test]
+pub fn test_custom_attr_roundtrip() {
+    let packet = new_request(VendorLevel { level: 2 }.into());
+    let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+
+    let attr = packet.get::<VendorLevel>().unwrap().unwrap();
+    assert_eq!(attr.level, 2);
+}
+
+#[test]
+pub fn test_custom_attr_validate() {
+    let mut registry = AttrRegistry::new();
+    registry.register::<VendorLevel>();
+    assert!(registry.contains(ATTR_VENDOR_LEVEL));
+
+    let good = new_request(VendorLevel { level: 3 }.into());
+    assert!(good.validate_with(&registry).is_none());
+
+    let bad = new_request(VendorLevel { level: 9 }.into());
+    assert!(bad.validate_with(&registry).is_some());
+
+    // 没有注册时当作未知 attribute, 不检查
+    assert!(bad.validate().is_none());
+
+    let short = new_request(RawAttr::new(ATTR_VENDOR_LEVEL, Bytes::from_static(&[1])));
+    assert!(short.validate_with(&registry).is_some());
+}
+
+#[test]
+pub fn test_custom_attr_packet_ref() {
+    let mut registry = AttrRegistry::new();
+    registry.register::<VendorLevel>().register::<VendorFlag>();
+
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+    let packet = Packet::new(
+        header,
+        vec![VendorFlag.into(), VendorLevel { level: 9 }.into()],
+    );
+    let buf = packet.pack().unwrap();
+    let packet = PacketRef::parse(&buf).unwrap();
+
+    // 没有注册时 vendor-flag 是不认识的 comprehension-required attribute
+    assert!(packet.has_unknown_attrs());
+    assert!(!packet.has_unknown_attrs_with(&registry));
+    assert_eq!(packet.to_packet().unknown_attrs(), vec![ATTR_VENDOR_FLAG]);
+    assert!(packet.to_packet().unknown_attrs_with(&registry).is_empty());
+
+    assert!(packet.validate().is_none());
+    assert!(packet.validate_with(&registry).is_some());
+    assert_eq!(
+        packet.validate_with(&registry),
+        packet.to_packet().validate_with(&registry)
+    );
+}
+
+#[test]
+pub fn test_custom_attr_decode() {
+    let mut registry = AttrRegistry::new();
+    registry.register::<VendorLevel>();
+    assert_eq!(registry.name(ATTR_VENDOR_LEVEL), Some("VENDOR-LEVEL"));
+
+    let packet = new_request(VendorLevel { level: 2 }.into());
+    let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+
+    // 没有注册时是 Unknown
+    match packet.attributes().next() {
+        Some(Ok(Attribute::Unknown(v))) => assert_eq!(v.attr_type, ATTR_VENDOR_LEVEL),
+        v => panic!("{:?}", v),
+    }
+
+    let attr = match packet.attributes_with(&registry).next() {
+        Some(Ok(Attribute::Custom(v))) => v,
+        v => panic!("{:?}", v),
+    };
+    assert_eq!(attr.name(), "VENDOR-LEVEL");
+    assert_eq!(attr.get::<VendorLevel>().unwrap().unwrap().level, 2);
+
+    let attr = Attribute::Custom(attr);
+    assert_eq!(attr.attr_type(), ATTR_VENDOR_LEVEL);
+    assert_eq!(attr.to_string(), "VENDOR-LEVEL (0xc001): level 2");
+
+    let raw: RawAttr = attr.into();
+    assert_eq!(raw.value.as_ref(), &[0, 0, 0, 2]);
+
+    // 注册之后解析失败和内置的 attribute 一样返回错误
+    let short = new_request(RawAttr::new(ATTR_VENDOR_LEVEL, Bytes::from_static(&[1])));
+    assert!(short.attributes_with(&registry).next().unwrap().is_err());
+}
+
+#[test]
+pub fn test_custom_attr_display() {
+    let mut registry = AttrRegistry::new();
+    registry.register::<VendorLevel>();
+
+    let packet = new_request(VendorLevel { level: 2 }.into());
+    assert!(packet
+        .to_string()
+        .ends_with("UNKNOWN (0xc001), 4 bytes: 00000002"));
+    assert!(packet
+        .display_with(&registry)
+        .to_string()
+        .ends_with("VENDOR-LEVEL (0xc001), 4 bytes: level 2"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+pub fn test_custom_attr_serialize() {
+    let mut registry = AttrRegistry::new();
+    registry.register::<VendorLevel>();
+
+    let packet = new_request(VendorLevel { level: 2 }.into());
+    let attr = packet.attributes_with(&registry).next().unwrap().unwrap();
+    assert_eq!(
+        serde_json::to_value(&attr).unwrap(),
+        serde_json::json!({"name": "VENDOR-LEVEL", "type": "0xc001", "value": "level 2"})
+    );
+}
@@ -0,0 +1,308 @@
+use bytes::Bytes;
+use std::net::SocketAddr;
+use stun_rs::attrs::attribute::Attribute;
+use stun_rs::attrs::fingerprint::Fingerprint;
+use stun_rs::attrs::message_integrity::MessageIntegrity;
+use stun_rs::attrs::nonce::Nonce;
+use stun_rs::attrs::realm::Realm;
+use stun_rs::attrs::software::Software;
+use stun_rs::attrs::username::Username;
+use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::auth;
+use stun_rs::classify::{self, PacketKind};
+use stun_rs::constants::*;
+use stun_rs::error::{ParsePacketErr, ValidateErr};
+use stun_rs::packet::{PackOptions, Packet};
+use stun_rs::packet_ref::PacketRef;
+
+// rfc 5769, test vectors for stun
+// padding 使用 0x20 (空格), 检查实现没有假设 padding 是 0
+
+const TRANS_ID: [u8; TRANS_ID_LEN] = [
+    0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34, 0xd6, 0x86, 0xfa, 0x87, 0xdf, 0xae,
+];
+
+const SHORT_TERM_PASSWORD: &str = "VOkJxbRl1RmTxUk/WvJxBt";
+
+// rfc 5769, 2.1
+const SAMPLE_REQUEST: &[u8] = &[
+    0x00, 0x01, 0x00, 0x58, // request type and message length
+    0x21, 0x12, 0xa4, 0x42, // magic cookie
+    0xb7, 0xe7, 0xa7, 0x01, // }
+    0xbc, 0x34, 0xd6, 0x86, // }  transaction id
+    0xfa, 0x87, 0xdf, 0xae, // }
+    0x80, 0x22, 0x00, 0x10, // SOFTWARE attribute header
+    0x53, 0x54, 0x55, 0x4e, // }
+    0x20, 0x74, 0x65, 0x73, // }  user agent...
+    0x74, 0x20, 0x63, 0x6c, // }  ...name
+    0x69, 0x65, 0x6e, 0x74, // }
+    0x00, 0x24, 0x00, 0x04, // PRIORITY attribute header
+    0x6e, 0x00, 0x01, 0xff, // ice priority value
+    0x80, 0x29, 0x00, 0x08, // ICE-CONTROLLED attribute header
+    0x93, 0x2f, 0xf9, 0xb1, // }  pseudo-random tie breaker...
+    0x51, 0x26, 0x3b, 0x36, // }   ...for ice control
+    0x00, 0x06, 0x00, 0x09, // USERNAME attribute header
+    0x65, 0x76, 0x74, 0x6a, // }
+    0x3a, 0x68, 0x36, 0x76, // }  username (9 bytes) and padding (3 bytes)
+    0x59, 0x20, 0x20, 0x20, // }
+    0x00, 0x08, 0x00, 0x14, // MESSAGE-INTEGRITY attribute header
+    0x9a, 0xea, 0xa7, 0x0c, // }
+    0xbf, 0xd8, 0xcb, 0x56, // }
+    0x78, 0x1e, 0xf2, 0xb5, // }  hmac-sha1 fingerprint
+    0xb2, 0xd3, 0xf2, 0x49, // }
+    0xc1, 0xb5, 0x71, 0xa2, // }
+    0x80, 0x28, 0x00, 0x04, // FINGERPRINT attribute header
+    0xe5, 0x7a, 0x3b, 0xcf, // crc32 fingerprint
+];
+
+// rfc 5769, 2.2
+const SAMPLE_IPV4_RESPONSE: &[u8] = &[
+    0x01, 0x01, 0x00, 0x3c, // response type and message length
+    0x21, 0x12, 0xa4, 0x42, // magic cookie
+    0xb7, 0xe7, 0xa7, 0x01, // }
+    0xbc, 0x34, 0xd6, 0x86, // }  transaction id
+    0xfa, 0x87, 0xdf, 0xae, // }
+    0x80, 0x22, 0x00, 0x0b, // SOFTWARE attribute header
+    0x74, 0x65, 0x73, 0x74, // }
+    0x20, 0x76, 0x65, 0x63, // }  utf-8 server name
+    0x74, 0x6f, 0x72, 0x20, // }
+    0x00, 0x20, 0x00, 0x08, // XOR-MAPPED-ADDRESS attribute header
+    0x00, 0x01, 0xa1, 0x47, // address family (ipv4) and xor'd mapped port number
+    0xe1, 0x12, 0xa6, 0x43, // xor'd mapped ipv4 address
+    0x00, 0x08, 0x00, 0x14, // MESSAGE-INTEGRITY attribute header
+    0x2b, 0x91, 0xf5, 0x99, // }
+    0xfd, 0x9e, 0x90, 0xc3, // }
+    0x8c, 0x74, 0x89, 0xf9, // }  hmac-sha1 fingerprint
+    0x2a, 0xf9, 0xba, 0x53, // }
+    0xf0, 0x6b, 0xe7, 0xd7, // }
+    0x80, 0x28, 0x00, 0x04, // FINGERPRINT attribute header
+    0xc0, 0x7d, 0x4c, 0x96, // crc32 fingerprint
+];
+
+// rfc 5769, 2.3
+const SAMPLE_IPV6_RESPONSE: &[u8] = &[
+    0x01, 0x01, 0x00, 0x48, // response type and message length
+    0x21, 0x12, 0xa4, 0x42, // magic cookie
+    0xb7, 0xe7, 0xa7, 0x01, // }
+    0xbc, 0x34, 0xd6, 0x86, // }  transaction id
+    0xfa, 0x87, 0xdf, 0xae, // }
+    0x80, 0x22, 0x00, 0x0b, // SOFTWARE attribute header
+    0x74, 0x65, 0x73, 0x74, // }
+    0x20, 0x76, 0x65, 0x63, // }  utf-8 server name
+    0x74, 0x6f, 0x72, 0x20, // }
+    0x00, 0x20, 0x00, 0x14, // XOR-MAPPED-ADDRESS attribute header
+    0x00, 0x02, 0xa1, 0x47, // address family (ipv6) and xor'd mapped port number
+    0x01, 0x13, 0xa9, 0xfa, // }
+    0xa5, 0xd3, 0xf1, 0x79, // }  xor'd mapped ipv6 address
+    0xbc, 0x25, 0xf4, 0xb5, // }
+    0xbe, 0xd2, 0xb9, 0xd9, // }
+    0x00, 0x08, 0x00, 0x14, // MESSAGE-INTEGRITY attribute header
+    0xa3, 0x82, 0x95, 0x4e, // }
+    0x4b, 0xe6, 0x7b, 0xf1, // }
+    0x17, 0x84, 0xc9, 0x7c, // }  hmac-sha1 fingerprint
+    0x82, 0x92, 0xc2, 0x75, // }
+    0xbf, 0xe3, 0xed, 0x41, // }
+    0x80, 0x28, 0x00, 0x04, // FINGERPRINT attribute header
+    0xc8, 0xfb, 0x0b, 0x4c, // crc32 fingerprint
+];
+
+// rfc 5769, 2.4
+const SAMPLE_LONG_TERM_REQUEST: &[u8] = &[
+    0x00, 0x01, 0x00, 0x60, // request type and message length
+    0x21, 0x12, 0xa4, 0x42, // magic cookie
+    0x78, 0xad, 0x34, 0x33, // }
+    0xc6, 0xad, 0x72, 0xc0, // }  transaction id
+    0x29, 0xda, 0x41, 0x2e, // }
+    0x00, 0x06, 0x00, 0x12, // USERNAME attribute header
+    0xe3, 0x83, 0x9e, 0xe3, // }
+    0x83, 0x88, 0xe3, 0x83, // }
+    0xaa, 0xe3, 0x83, 0x83, // }  username value (18 bytes) and padding (2 bytes)
+    0xe3, 0x82, 0xaf, 0xe3, // }
+    0x82, 0xb9, 0x00, 0x00, // }
+    0x00, 0x15, 0x00, 0x1c, // NONCE attribute header
+    0x66, 0x2f, 0x2f, 0x34, // }
+    0x39, 0x39, 0x6b, 0x39, // }
+    0x35, 0x34, 0x64, 0x36, // }
+    0x4f, 0x4c, 0x33, 0x34, // }  nonce value
+    0x6f, 0x4c, 0x39, 0x46, // }
+    0x53, 0x54, 0x76, 0x79, // }
+    0x36, 0x34, 0x73, 0x41, // }
+    0x00, 0x14, 0x00, 0x0b, // REALM attribute header
+    0x65, 0x78, 0x61, 0x6d, // }
+    0x70, 0x6c, 0x65, 0x2e, // }  realm value (11 bytes) and padding (1 byte)
+    0x6f, 0x72, 0x67, 0x00, // }
+    0x00, 0x08, 0x00, 0x14, // MESSAGE-INTEGRITY attribute header
+    0xf6, 0x70, 0x24, 0x65, // }
+    0x6d, 0xd6, 0x4a, 0x3e, // }
+    0x02, 0xb8, 0xe0, 0x71, // }  hmac-sha1 fingerprint
+    0x2e, 0x85, 0xc9, 0xa2, // }
+    0x8c, 0xa8, 0x96, 0x66, // }
+];
+
+// 解析, 检查 fingerprint, 重新编码和原来的字节一致
+fn unpack(buf: &'static [u8]) -> Packet {
+    assert_eq!(classify::classify(buf), PacketKind::Stun);
+
+    let packet = Packet::unpack(Bytes::from_static(buf)).unwrap();
+    assert!(packet.validate().is_none());
+    assert!(packet.verify_fingerprint().is_none());
+    assert_eq!(packet.pack().unwrap().as_ref(), buf);
+
+    let packet_ref = PacketRef::parse(buf).unwrap();
+    assert!(packet_ref.validate().is_none());
+    assert_eq!(packet_ref.to_packet().pack().unwrap().as_ref(), buf);
+
+    packet
+}
+
+fn check_fingerprint(packet: &Packet, crc: u32) {
+    let fingerprint = packet.get::<Fingerprint>().unwrap().unwrap();
+    assert_eq!(fingerprint.crc, crc);
+    assert_eq!(packet.attrs.last().unwrap().attr_type, ATTR_FINGERPRINT);
+
+    // 重新计算 fingerprint 时使用收到的 padding
+    let options = PackOptions {
+        fingerprint: true,
+        ..Default::default()
+    };
+    assert_eq!(packet.pack_with(&options).unwrap(), packet.pack().unwrap());
+}
+
+fn check_response(buf: &'static [u8], address: &str, crc: u32) {
+    let packet = unpack(buf);
+    assert_eq!(packet.header.msg_type, MESSAGE_TYPE_BIND_RES);
+    assert_eq!(packet.header.trans_id, TRANS_ID);
+    assert!(packet.unknown_attrs().is_empty());
+
+    let attr_types: Vec<u16> = packet.attrs.iter().map(|x| x.attr_type).collect();
+    assert_eq!(
+        attr_types,
+        vec![
+            ATTR_SOFTWARE,
+            ATTR_XOR_MAPPED_ADDRESS,
+            ATTR_MESSAGE_INTEGRITY,
+            ATTR_FINGERPRINT
+        ]
+    );
+
+    let software = packet.get::<Software>().unwrap().unwrap();
+    assert_eq!(software.software, "test vector");
+    assert_eq!(packet.attrs[0].padding.as_ref(), b" ");
+
+    let address: SocketAddr = address.parse().unwrap();
+    let xor_address = packet.get::<XorMappedAddress>().unwrap().unwrap();
+    assert_eq!(xor_address.address, address);
+
+    let key = auth::short_term_key(SHORT_TERM_PASSWORD);
+    assert!(packet.verify_message_integrity(&key).is_none());
+    assert!(packet.verify_message_integrity(b"wrong").is_some());
+    check_fingerprint(&packet, crc);
+
+    // 重新生成 xor-mapped-address, message-integrity, fingerprint
+    let mut rebuilt = Packet::new(
+        packet.header.clone(),
+        vec![
+            packet.attrs[0].clone(),
+            XorMappedAddress::new(TRANS_ID, address).into(),
+        ],
+    );
+    rebuilt.add_message_integrity(&key).unwrap();
+    rebuilt.add_fingerprint().unwrap();
+    assert_eq!(rebuilt.pack().unwrap().as_ref(), buf);
+}
+
+#[test]
+pub fn test_rfc5769_request() {
+    let packet = unpack(SAMPLE_REQUEST);
+    assert_eq!(packet.header.msg_type, MESSAGE_TYPE_BIND_REQ);
+    assert_eq!(packet.header.trans_id, TRANS_ID);
+
+    // PRIORITY 是 ice 的, 不认识的 comprehension-required attribute
+    assert_eq!(packet.unknown_attrs(), vec![0x0024]);
+
+    let software = packet.get::<Software>().unwrap().unwrap();
+    assert_eq!(software.software, "STUN test client");
+
+    let username = packet.get::<Username>().unwrap().unwrap();
+    assert_eq!(username.username, "evtj:h6vY");
+    assert_eq!(packet.attrs[3].padding.as_ref(), b"   ");
+
+    match packet.attributes().nth(2) {
+        Some(Ok(Attribute::Unknown(v))) => {
+            assert_eq!(v.attr_type, 0x8029);
+            assert_eq!(
+                v.value.as_ref(),
+                &[0x93, 0x2f, 0xf9, 0xb1, 0x51, 0x26, 0x3b, 0x36]
+            );
+        }
+        v => panic!("{:?}", v),
+    };
+
+    let mi = packet.get::<MessageIntegrity>().unwrap().unwrap();
+    assert_eq!(mi.hmac, SAMPLE_REQUEST[80..100]);
+
+    let key = auth::short_term_key(SHORT_TERM_PASSWORD);
+    assert!(packet.verify_message_integrity(&key).is_none());
+    check_fingerprint(&packet, 0xe57a3bcf);
+
+    // padding 改成 0 之后 fingerprint 和 message-integrity 都不对
+    let mut zero_padding = SAMPLE_REQUEST.to_vec();
+    zero_padding[69..72].copy_from_slice(&[0, 0, 0]);
+    assert!(matches!(
+        Packet::unpack(Bytes::from(zero_padding.clone())).unwrap_err(),
+        ParsePacketErr::FingerprintMismatch { .. }
+    ));
+
+    // 去掉 fingerprint
+    zero_padding.truncate(zero_padding.len() - 8);
+    zero_padding[3] -= 8;
+    let packet = Packet::unpack(Bytes::from(zero_padding)).unwrap();
+    assert_eq!(
+        packet.verify_message_integrity(&key),
+        Some(ValidateErr::IntegrityMismatch(ATTR_MESSAGE_INTEGRITY))
+    );
+}
+
+#[test]
+pub fn test_rfc5769_ipv4_response() {
+    check_response(SAMPLE_IPV4_RESPONSE, "192.0.2.1:32853", 0xc07d4c96);
+}
+
+#[test]
+pub fn test_rfc5769_ipv6_response() {
+    check_response(
+        SAMPLE_IPV6_RESPONSE,
+        "[2001:db8:1234:5678:11:2233:4455:6677]:32853",
+        0xc8fb0b4c,
+    );
+}
+
+#[test]
+pub fn test_rfc5769_long_term_request() {
+    let packet = unpack(SAMPLE_LONG_TERM_REQUEST);
+    assert_eq!(packet.header.msg_type, MESSAGE_TYPE_BIND_REQ);
+    assert!(packet.unknown_attrs().is_empty());
+
+    // U+30DE U+30C8 U+30EA U+30C3 U+30AF U+30B9
+    let username = packet.get::<Username>().unwrap().unwrap();
+    assert_eq!(username.username, "マトリックス");
+
+    let nonce = packet.get::<Nonce>().unwrap().unwrap();
+    assert_eq!(nonce.nonce, "f//499k954d6OL34oL9FSTvy64sA");
+
+    let realm = packet.get::<Realm>().unwrap().unwrap();
+    assert_eq!(realm.realm, "example.org");
+
+    let mi = packet.get::<MessageIntegrity>().unwrap().unwrap();
+    assert_eq!(mi.hmac, SAMPLE_LONG_TERM_REQUEST[96..116]);
+    assert!(!packet.has_attr(ATTR_FINGERPRINT));
+
+    // password "The\u{00ad}M\u{00aa}tr\u{2168}", SASLprep 之后是 "TheMatrIX"
+    let key = auth::long_term_key(&username.username, &realm.realm, "TheMatrIX");
+    assert!(packet.verify_message_integrity(&key).is_none());
+
+    // 重新生成 message-integrity
+    let mut rebuilt = Packet::new(packet.header.clone(), packet.attrs[..3].to_vec());
+    rebuilt.add_message_integrity(&key).unwrap();
+    assert_eq!(rebuilt.pack().unwrap().as_ref(), SAMPLE_LONG_TERM_REQUEST);
+}
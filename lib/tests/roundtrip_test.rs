@@ -0,0 +1,121 @@
+use bytes::Bytes;
+use proptest::prelude::*;
+use std::net::{IpAddr, SocketAddr};
+use stun_rs::attrs::address_attr::AddressAttr;
+use stun_rs::attrs::change_request::ChangeRequest;
+use stun_rs::attrs::errcode_attr::ErrcodeAttr;
+use stun_rs::attrs::padding_attr::PaddingAttr;
+use stun_rs::attrs::response_port::ResponsePort;
+use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::attrs::RawAttr;
+use stun_rs::constants::*;
+use stun_rs::header::Header;
+use stun_rs::packet::Packet;
+use stun_rs::util;
+
+// 编码再解码, 结果和原来的一样
+
+// 编码成网络字节再解析
+fn wire(raw: RawAttr) -> RawAttr {
+    let buf = raw.pack();
+    assert_eq!(buf.len(), raw.len());
+    RawAttr::unpack(buf).unwrap()
+}
+
+// ipv6 的 flowinfo, scope_id 不会编码, 只生成 0
+fn socket_addr() -> impl Strategy<Value = SocketAddr> {
+    prop_oneof![
+        (any::<[u8; 4]>(), any::<u16>())
+            .prop_map(|(ip, port)| SocketAddr::new(IpAddr::from(ip), port)),
+        (any::<[u8; 16]>(), any::<u16>())
+            .prop_map(|(ip, port)| SocketAddr::new(IpAddr::from(ip), port)),
+    ]
+}
+
+fn address_attr_type() -> impl Strategy<Value = u16> {
+    prop::sample::select(vec![
+        ATTR_MAPPED_ADDRESS,
+        ATTR_SOURCE_ADDRESS,
+        ATTR_CHANGED_ADDRESS,
+        ATTR_RESPONSE_ORIGIN,
+        ATTR_OTHER_ADDRESS,
+        ATTR_ALTERNATE_SERVER,
+    ])
+}
+
+// class 0-7, number 0-99
+fn error_code() -> impl Strategy<Value = u16> {
+    (0_u16..8, 0_u16..100).prop_map(|(class, number)| class * 100 + number)
+}
+
+proptest! {
+    #[test]
+    fn test_error_code_pack(code in error_code()) {
+        prop_assert_eq!(util::unpack_error_code(util::pack_error_code(code)), code);
+    }
+
+    #[test]
+    fn test_address_attr(attr_type in address_attr_type(), address in socket_addr()) {
+        let raw = wire(AddressAttr::new(attr_type, address).into());
+        prop_assert_eq!(raw.attr_type, attr_type);
+
+        let attr = AddressAttr::try_from(raw).unwrap();
+        prop_assert_eq!(attr.attr_type, attr_type);
+        prop_assert_eq!(attr.address, address);
+    }
+
+    #[test]
+    fn test_xor_mapped_address(trans_id in any::<[u8; TRANS_ID_LEN]>(), address in socket_addr()) {
+        let header = Header::new(MESSAGE_TYPE_BIND_RES, 0, trans_id);
+        let raw = wire(XorMappedAddress::new(trans_id, address).into());
+        let attr = XorMappedAddress::from_base_attr(raw, &header).unwrap();
+        prop_assert_eq!(attr.address, address);
+
+        // 整个包编码, 按 header 的 transaction id 解析
+        let packet = Packet::new(header, vec![XorMappedAddress::new(trans_id, address).into()]);
+        let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+        let attr = packet.get::<XorMappedAddress>().unwrap().unwrap();
+        prop_assert_eq!(attr.address, address);
+        prop_assert_eq!(attr.trans_id, trans_id);
+    }
+
+    // 解析时会去掉 reason 前后的空白
+    #[test]
+    fn test_errcode_attr(code in error_code(), msg in "([^\\s](.{0,40}[^\\s])?)?") {
+        let raw = wire(ErrcodeAttr::new(code, &msg).into());
+        prop_assert_eq!(raw.value.len() % 4, 0);
+
+        let attr = ErrcodeAttr::try_from(raw).unwrap();
+        prop_assert_eq!(attr.code, code);
+        prop_assert_eq!(attr.msg, msg);
+    }
+
+    #[test]
+    fn test_change_request(change_ip in any::<bool>(), change_port in any::<bool>()) {
+        let raw = wire(ChangeRequest::new(change_ip, change_port).into());
+        let attr = ChangeRequest::try_from(raw).unwrap();
+        prop_assert_eq!(attr.change_ip, change_ip);
+        prop_assert_eq!(attr.change_port, change_port);
+    }
+
+    #[test]
+    fn test_response_port(port in any::<u16>()) {
+        let raw = wire(ResponsePort::new(port).into());
+        let attr = ResponsePort::try_from(raw).unwrap();
+        prop_assert_eq!(attr.port, port);
+    }
+
+    // 长度是 8 的倍数
+    #[test]
+    fn test_padding_attr(data in (0_usize..256).prop_flat_map(|n| prop::collection::vec(any::<u8>(), n * 8))) {
+        let raw = wire(PaddingAttr::new(Bytes::from(data.clone())).into());
+        let attr = PaddingAttr::try_from(raw).unwrap();
+        prop_assert_eq!(attr.data.as_ref(), &data[..]);
+    }
+
+    #[test]
+    fn test_padding_attr_len(data in prop::collection::vec(any::<u8>(), 0..2048)) {
+        let raw = wire(PaddingAttr::new(Bytes::from(data.clone())).into());
+        prop_assert_eq!(PaddingAttr::try_from(raw).is_ok(), data.len() % 8 == 0);
+    }
+}
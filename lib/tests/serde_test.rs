@@ -0,0 +1,66 @@
+#![cfg(feature = "serde")]
+
+use bytes::Bytes;
+use serde_json::json;
+use std::net::SocketAddr;
+use stun_rs::attrs::address_attr::AddressAttr;
+use stun_rs::attrs::software::Software;
+use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::attrs::RawAttr;
+use stun_rs::constants::*;
+use stun_rs::header::Header;
+use stun_rs::packet::Packet;
+
+#[test]
+pub fn test_serialize_packet() {
+    let trans_id = [1_u8; TRANS_ID_LEN];
+    let mapped: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+    let header = Header::new(MESSAGE_TYPE_BIND_RES, 0, trans_id);
+    let packet = Packet::new(
+        header,
+        vec![
+            AddressAttr::new(ATTR_MAPPED_ADDRESS, mapped).into(),
+            XorMappedAddress::new(trans_id, mapped).into(),
+            Software::new("server 0.1.0").into(),
+            RawAttr::new(0x8100, Bytes::from_static(&[0xde, 0xad])),
+        ],
+    );
+    let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+
+    let value = serde_json::to_value(&packet).unwrap();
+    assert_eq!(
+        value["header"],
+        json!({
+            "msg_type": {"method": "Binding", "class": "Success Response", "value": "0x0101"},
+            "msg_len": 48,
+            "magic_cookie": "2112a442",
+            "trans_id": "010101010101010101010101",
+        })
+    );
+    assert_eq!(
+        value["attributes"],
+        json!([
+            {"name": "MAPPED-ADDRESS", "type": "0x0001", "value": "1.2.3.4:5678"},
+            {"name": "XOR-MAPPED-ADDRESS", "type": "0x0020", "value": "1.2.3.4:5678"},
+            {"name": "SOFTWARE", "type": "0x8022", "value": "server 0.1.0"},
+            {"name": "UNKNOWN", "type": "0x8100", "length": 2, "value": "dead"},
+        ])
+    );
+}
+
+#[test]
+pub fn test_serialize_bad_attr() {
+    // 解析失败的 attribute 输出原始数据
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, [2_u8; TRANS_ID_LEN]);
+    let packet = Packet::new(
+        header,
+        vec![RawAttr::new(ATTR_CHANGE_REQUEST, Bytes::from_static(&[0, 0]))],
+    );
+
+    let value = serde_json::to_value(&packet).unwrap();
+    assert_eq!(value["header"]["msg_type"]["class"], "Request");
+    assert_eq!(
+        value["attributes"][0],
+        json!({"name": "CHANGE-REQUEST", "type": "0x0003", "length": 2, "value": "0000"})
+    );
+}
@@ -0,0 +1,46 @@
+use bytes::Bytes;
+use stun_rs::attrs::attribute::Attribute;
+use stun_rs::attrs::unknown_attributes::UnknownAttributes;
+use stun_rs::attrs::RawAttr;
+use stun_rs::constants::*;
+use stun_rs::header::Header;
+use stun_rs::packet::Packet;
+use stun_rs::util;
+
+#[test]
+pub fn test_unknown_attributes_pack() {
+    let header = Header::new(MESSAGE_TYPE_BIND_ERR_RES, 0, util::new_trans_id());
+    let attr = UnknownAttributes::new(vec![0x0002, 0x7f00, 0x0031]);
+    let packet = Packet::new(header, vec![attr.clone().into()]);
+
+    // 6 字节, padding 到 8 字节
+    assert_eq!(packet.header.msg_len, 4 + 8);
+
+    let packet = Packet::unpack(packet.pack().unwrap()).unwrap();
+    let decoded = packet.get::<UnknownAttributes>().unwrap().unwrap();
+    assert_eq!(decoded, attr);
+
+    let raw = RawAttr::new(ATTR_UNKNOWN_ATTRIBUTES, Bytes::from_static(&[0, 1, 2]));
+    assert!(UnknownAttributes::try_from(raw).is_err());
+}
+
+#[test]
+pub fn test_unknown_comprehension_required() {
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+    let attrs = vec![
+        RawAttr::new(0x7f01, Bytes::from_static(&[1, 2, 3, 4])),
+        RawAttr::new(0xc001, Bytes::from_static(&[1, 2, 3, 4])),
+        RawAttr::new(0x7f01, Bytes::from_static(&[5, 6, 7, 8])),
+        RawAttr::new(0x0002, Bytes::new()),
+    ];
+    let packet = Packet::new(header, attrs);
+
+    // comprehension-optional 的 0xc001 被忽略, 重复的只列一次
+    assert_eq!(packet.unknown_attrs(), vec![0x7f01, 0x0002]);
+    assert!(!Attribute::is_known(0x7f01));
+    assert!(Attribute::is_known(ATTR_UNKNOWN_ATTRIBUTES));
+
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+    let attrs = vec![RawAttr::new(0xc001, Bytes::from_static(&[1, 2, 3, 4]))];
+    assert!(Packet::new(header, attrs).unknown_attrs().is_empty());
+}
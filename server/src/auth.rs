@@ -0,0 +1,243 @@
+use log::debug;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use stun_rs::attrs::nonce::Nonce;
+use stun_rs::attrs::password_algorithm::{PasswordAlgorithm, PasswordAlgorithms};
+use stun_rs::attrs::realm::Realm;
+use stun_rs::attrs::userhash::Userhash;
+use stun_rs::attrs::username::Username;
+use stun_rs::auth::{hmac_sha1, long_term_key_with, nonce_cookie, userhash};
+use stun_rs::constants::*;
+use stun_rs::packet::Packet;
+
+// rfc 5389, 10.2 / rfc 8489, 9.2
+// long-term credential, 服务端的验证流程
+//
+// 没有 message-integrity(-sha256) -> 401, 带 realm, nonce, password-algorithms
+// 缺少 username(userhash) / realm / nonce -> 400
+// nonce 过期或者不是本服务发出的 -> 438, 带 realm, nonce, password-algorithms
+// password-algorithms 和服务端发出的不一致 (bid-down) -> 400
+// 用户不存在 / message-integrity 不匹配 -> 401, 带 realm, nonce, password-algorithms
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityKind {
+    Sha1,
+    Sha256,
+}
+
+pub enum AuthResult {
+    // 验证通过, 响应包需要用这个 key 计算和请求一样的 message-integrity
+    Pass(Vec<u8>, IntegrityKind),
+
+    // 401 / 438, 响应包需要带上 realm 和新的 nonce
+    Challenge(u16),
+
+    // 400
+    BadRequest,
+}
+
+pub struct Authenticator {
+    realm: String,
+
+    // username -> password
+    users: HashMap<String, String>,
+
+    // userhash -> username
+    userhashes: HashMap<[u8; USERHASH_LEN], String>,
+
+    // 支持的 password algorithm, 按优先级排列
+    algorithms: Vec<u16>,
+
+    // 用于生成 nonce, 不需要保存已经发出去的 nonce
+    secret: [u8; 16],
+    nonce_lifetime: Duration,
+}
+
+impl Authenticator {
+    pub fn new(
+        realm: &str,
+        users: HashMap<String, String>,
+        algorithms: Vec<u16>,
+        nonce_lifetime: Duration,
+    ) -> Self {
+        let mut secret = [0_u8; 16];
+        rand::thread_rng().fill_bytes(&mut secret);
+
+        let userhashes = users
+            .keys()
+            .map(|x| (userhash(x, realm), x.clone()))
+            .collect();
+
+        Self {
+            realm: realm.to_string(),
+            users,
+            userhashes,
+            algorithms,
+            secret,
+            nonce_lifetime,
+        }
+    }
+
+    pub fn realm(&self) -> &str {
+        &self.realm
+    }
+
+    pub fn password_algorithms(&self) -> PasswordAlgorithms {
+        let list = self
+            .algorithms
+            .iter()
+            .map(|x| PasswordAlgorithm::new(*x))
+            .collect();
+        PasswordAlgorithms::new(list)
+    }
+
+    // nonce = nonce cookie + 时间戳(16 hex) + hmac(时间戳, 客户端ip) 的前 8 字节(16 hex)
+    pub fn new_nonce(&self, remote_ip: IpAddr) -> String {
+        self.make_nonce(unix_timestamp(), remote_ip)
+    }
+
+    fn make_nonce(&self, timestamp: u64, remote_ip: IpAddr) -> String {
+        let mut input = timestamp.to_be_bytes().to_vec();
+        match remote_ip {
+            IpAddr::V4(v) => input.extend_from_slice(&v.octets()),
+            IpAddr::V6(v) => input.extend_from_slice(&v.octets()),
+        }
+
+        let mac = hmac_sha1(&self.secret, &input);
+        let sign: String = mac[..8].iter().map(|x| format!("{:02x}", x)).collect();
+
+        let features = SECURITY_FEATURE_PASSWORD_ALGORITHMS | SECURITY_FEATURE_USERNAME_ANONYMITY;
+        format!("{}{:016x}{}", nonce_cookie(features), timestamp, sign)
+    }
+
+    fn check_nonce(&self, nonce: &str, remote_ip: IpAddr) -> bool {
+        // 按字节切分, nonce 可能不是 ascii
+        if nonce.len() != NONCE_COOKIE_LEN + 32 || !nonce.is_ascii() {
+            return false;
+        }
+
+        let timestamp = &nonce[NONCE_COOKIE_LEN..NONCE_COOKIE_LEN + 16];
+        let timestamp = match u64::from_str_radix(timestamp, 16) {
+            Ok(v) => v,
+            Err(_) => return false,
+        };
+
+        if self.make_nonce(timestamp, remote_ip) != nonce {
+            return false;
+        }
+
+        unix_timestamp().saturating_sub(timestamp) <= self.nonce_lifetime.as_secs()
+    }
+
+    pub fn check(&self, req: &Packet, remote_ip: IpAddr) -> AuthResult {
+        let has_sha1 = req.has_attr(ATTR_MESSAGE_INTEGRITY);
+        let has_sha256 = req.has_attr(ATTR_MESSAGE_INTEGRITY_SHA256);
+        if !has_sha1 && !has_sha256 {
+            return AuthResult::Challenge(ERROR_CODE_UNAUTHORIZED);
+        }
+
+        let realm = req.get::<Realm>().ok().flatten();
+        let nonce = req.get::<Nonce>().ok().flatten();
+
+        let (realm, nonce) = match (realm, nonce) {
+            (Some(r), Some(n)) => (r.realm, n.nonce),
+            _ => return AuthResult::BadRequest,
+        };
+
+        // username 或者 userhash
+        let username = req.get::<Username>().ok().flatten();
+        let hash = req.get::<Userhash>().ok().flatten();
+        let username = match (username, hash) {
+            (Some(u), _) => u.username,
+            (None, Some(h)) => match self.userhashes.get(&h.hash) {
+                None => {
+                    debug!("unknown userhash, from: {}", remote_ip);
+                    return AuthResult::Challenge(ERROR_CODE_UNAUTHORIZED);
+                }
+                Some(v) => v.clone(),
+            },
+            _ => return AuthResult::BadRequest,
+        };
+
+        if !self.check_nonce(&nonce, remote_ip) {
+            debug!("stale nonce: {}, from: {}", nonce, remote_ip);
+            return AuthResult::Challenge(ERROR_CODE_STALE_NONCE);
+        }
+
+        let algorithm = match self.select_algorithm(req) {
+            None => {
+                debug!("password algorithm not match, from: {}", remote_ip);
+                return AuthResult::BadRequest;
+            }
+            Some(v) => v,
+        };
+
+        if realm != self.realm {
+            debug!("wrong realm: {}, from: {}", realm, remote_ip);
+            return AuthResult::Challenge(ERROR_CODE_UNAUTHORIZED);
+        }
+
+        let password = match self.users.get(&username) {
+            None => {
+                debug!("unknown user: {}, from: {}", username, remote_ip);
+                return AuthResult::Challenge(ERROR_CODE_UNAUTHORIZED);
+            }
+            Some(v) => v,
+        };
+
+        let key = match long_term_key_with(algorithm, &username, &self.realm, password) {
+            None => return AuthResult::BadRequest,
+            Some(v) => v,
+        };
+
+        // 两个都有的时候, 使用 message-integrity-sha256
+        let (result, kind) = match has_sha256 {
+            true => (
+                req.verify_message_integrity_sha256(&key),
+                IntegrityKind::Sha256,
+            ),
+            false => (req.verify_message_integrity(&key), IntegrityKind::Sha1),
+        };
+
+        if let Some(e) = result {
+            debug!("user: {}, from: {}, {:?}", username, remote_ip, e);
+            return AuthResult::Challenge(ERROR_CODE_UNAUTHORIZED);
+        }
+
+        AuthResult::Pass(key, kind)
+    }
+
+    // rfc 8489, 9.2.4
+    // 请求里的 password-algorithms 必须和服务端在 nonce 中声明的一致, 防止 bid-down
+    // 两个都没有时按 md5 处理
+    fn select_algorithm(&self, req: &Packet) -> Option<u16> {
+        let algorithm = req.get::<PasswordAlgorithm>().ok().flatten();
+        let algorithms = req.get::<PasswordAlgorithms>().ok().flatten();
+
+        let algorithm = match (algorithm, algorithms) {
+            (None, None) => PASSWORD_ALGORITHM_MD5,
+            (Some(a), Some(list)) => {
+                if list != self.password_algorithms() {
+                    return None;
+                }
+                a.algorithm
+            }
+            _ => return None,
+        };
+
+        match self.algorithms.contains(&algorithm) {
+            true => Some(algorithm),
+            false => None,
+        }
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
+}
@@ -1,3 +1,4 @@
+pub mod auth;
 pub mod server;
 pub mod signal;
 pub mod stun;
@@ -1,13 +1,20 @@
 // ./server --ip1 1.2.3.4 --ip2 1.2.3.5 --port1 3478 --port2 3479
+// ./server --ip1 1.2.3.4 --ip2 1.2.3.5 --port1 3478 --port2 3479 --realm example.org --user alice:secret
 
 use log::{debug, error, info};
-use std::net::IpAddr;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
 
 use clap::builder::ValueParser;
 use clap::{Arg, Command};
+use stun_rs::attrs::software::Software;
+use stun_rs::constants::{PASSWORD_ALGORITHM_MD5, PASSWORD_ALGORITHM_SHA256};
+use stun_rs::packet::DecodeOptions;
 use tokio::sync::watch;
 
-use server::server::Server;
+use server::auth::Authenticator;
+use server::server::{Redirect, RedirectPolicy, Server, ServerConfig};
 use server::signal::wait_shutdown;
 
 const APP_NAME: &str = env!("CARGO_PKG_NAME");
@@ -36,6 +43,30 @@ fn parse_ip(s: &str) -> Result<IpAddr, String> {
     Ok(ip)
 }
 
+// sha256,md5
+fn parse_algorithms(s: &str) -> Result<Vec<u16>, String> {
+    let mut list = vec![];
+    for v in s.split(',') {
+        let algorithm = match v.trim() {
+            "sha256" => PASSWORD_ALGORITHM_SHA256,
+            "md5" => PASSWORD_ALGORITHM_MD5,
+            v => return Err(format!("not support password algorithm: {}", v)),
+        };
+        if !list.contains(&algorithm) {
+            list.push(algorithm);
+        }
+    }
+    Ok(list)
+}
+
+// username:password
+fn parse_user(s: &str) -> Result<(String, String), String> {
+    match s.split_once(':') {
+        Some((name, password)) if !name.is_empty() => Ok((name.to_string(), password.to_string())),
+        _ => Err("user format: username:password".to_string()),
+    }
+}
+
 #[tokio::main]
 async fn main() {
     env_logger::init();
@@ -75,6 +106,68 @@ async fn main() {
                 .help("alternative port")
                 .value_parser(clap::value_parser!(u16).range(0..65535)),
         )
+        .arg(
+            Arg::new("user")
+                .long("user")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("long-term credential, username:password")
+                .value_parser(ValueParser::new(parse_user)),
+        )
+        .arg(
+            Arg::new("realm")
+                .long("realm")
+                .takes_value(true)
+                .default_value(APP_NAME)
+                .help("realm for long-term credential"),
+        )
+        .arg(
+            Arg::new("password_algorithms")
+                .long("password_algorithms")
+                .takes_value(true)
+                .default_value("sha256,md5")
+                .help("password algorithms for long-term credential, in order of preference")
+                .value_parser(ValueParser::new(parse_algorithms)),
+        )
+        .arg(
+            Arg::new("nonce_lifetime")
+                .long("nonce_lifetime")
+                .takes_value(true)
+                .default_value("600")
+                .help("nonce lifetime in seconds")
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("strict_port")
+                .long("strict_port")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("port1 or port2, only accept rfc 5389 requests")
+                .value_parser(clap::value_parser!(u16)),
+        )
+        .arg(
+            Arg::new("lenient_port")
+                .long("lenient_port")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("port1 or port2, also accept unpadded rfc 3489 requests")
+                .value_parser(clap::value_parser!(u16)),
+        )
+        .arg(
+            Arg::new("alternate_server")
+                .long("alternate_server")
+                .takes_value(true)
+                .help("redirect binding requests to this server (300 try alternate)")
+                .value_parser(clap::value_parser!(SocketAddr)),
+        )
+        .arg(
+            Arg::new("redirect_queue")
+                .long("redirect_queue")
+                .takes_value(true)
+                .requires("alternate_server")
+                .help("only redirect when more requests than this are queued, default always")
+                .value_parser(clap::value_parser!(usize)),
+        )
         .get_matches();
 
     //
@@ -93,6 +186,57 @@ async fn main() {
 
     debug!("ip:{},{}  port:{},{}", ip1, ip2, port1, port2);
 
+    // 配置了用户才开启验证
+    let users: HashMap<String, String> = match app.get_many::<(String, String)>("user") {
+        None => HashMap::new(),
+        Some(v) => v.cloned().collect(),
+    };
+    let realm: &String = app.get_one("realm").expect("wrong realm");
+    let algorithms: &Vec<u16> = app
+        .get_one("password_algorithms")
+        .expect("wrong password_algorithms");
+    let nonce_lifetime: u64 = *app.get_one("nonce_lifetime").expect("wrong nonce_lifetime");
+
+    let mut config = ServerConfig::default();
+    if !users.is_empty() {
+        debug!("auth enable, realm: {}, users: {}", realm, users.len());
+        config.auth = Some(Authenticator::new(
+            realm,
+            users,
+            algorithms.clone(),
+            Duration::from_secs(nonce_lifetime),
+        ));
+    }
+
+    let decode_ports = [
+        ("strict_port", DecodeOptions::strict()),
+        ("lenient_port", DecodeOptions::lenient()),
+    ];
+    for (name, options) in decode_ports {
+        for port in app.get_many::<u16>(name).into_iter().flatten() {
+            if *port != port1 && *port != port2 {
+                panic!("error, {} {} is not port1 or port2", name, port);
+            }
+            debug!("{}: {}", name, port);
+            config.decode.insert(*port, options.clone());
+        }
+    }
+
+    // 配置了 alternate_server 才重定向
+    if let Some(alternate) = app.get_one::<SocketAddr>("alternate_server") {
+        let policy = match app.get_one::<usize>("redirect_queue") {
+            None => RedirectPolicy::Always,
+            Some(v) => RedirectPolicy::QueueAbove(*v),
+        };
+        debug!("redirect enable, alternate: {}", alternate);
+        config.redirect = Some(Redirect {
+            alternate: *alternate,
+            policy,
+        });
+    }
+
+    config.software = Some(Software::new(&format!("{} {}", APP_NAME, APP_VERSION)));
+
     let (signal_tx, signal_rx) = watch::channel(0_u8);
 
     let _signal_handle = tokio::spawn(async move {
@@ -105,7 +249,7 @@ async fn main() {
         };
     });
 
-    let server = match Server::new([ip1, ip2], [port1, port2], signal_rx).await {
+    let server = match Server::new([ip1, ip2], [port1, port2], config, signal_rx).await {
         Ok(v) => v,
         Err(e) => {
             panic!("error, {:?}", e);
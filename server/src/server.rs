@@ -15,38 +15,122 @@ use tokio::sync::watch::Receiver as WatchReceiver;
 
 use bytes::Bytes;
 use log::{debug, error};
-use stun_rs::util::print_bytes;
+use stun_rs::attrs::software::Software;
+use stun_rs::attrs::RawAttr;
+use stun_rs::constants::{ATTR_FINGERPRINT, ERROR_CODE_BAD_REQUEST};
+use stun_rs::packet::{DecodeOptions, PackOptions, Packet};
+use stun_rs::util::print_packet;
 
-use crate::stun::{get_bad_response, get_response, parse_request, send_response, validate_req};
+use crate::auth::{AuthResult, Authenticator, IntegrityKind};
+use crate::stun::{
+    encode_fast_response, get_alternate_response, get_challenge_response, get_change_flag,
+    get_error_response, get_response, get_unknown_attrs_response, parse_error_code, parse_request,
+    send_bytes, send_response, validate_req,
+};
 
 // local addr, remote addr, recv data
 type SocketInput = (SocketAddr, SocketAddr, Bytes);
 
+// 每个发送任务重复使用的 buf
+const SEND_BUF_LEN: usize = 4 * 1024;
+
+// recv_udp -> process_udp 的队列长度
+const QUEUE_LEN: usize = 100;
+
+#[derive(Default)]
+pub struct ServerConfig {
+    // None: 不需要验证
+    pub auth: Option<Authenticator>,
+
+    // 按本地端口选择解析方式, 没有配置的端口使用 DecodeOptions::default()
+    pub decode: HashMap<u16, DecodeOptions>,
+
+    // 每个响应都带上 software
+    pub software: Option<Software>,
+
+    // None: 不重定向
+    pub redirect: Option<Redirect>,
+}
+
+// rfc 8489, 10
+// 满足条件时回复 300, 让客户端去 alternate 请求
+pub struct Redirect {
+    pub alternate: SocketAddr,
+    pub policy: RedirectPolicy,
+}
+
+pub enum RedirectPolicy {
+    // 所有请求都重定向
+    Always,
+
+    // 队列中等待处理的请求数超过 threshold 时重定向
+    QueueAbove(usize),
+}
+
+impl Redirect {
+    // queued: 当前队列中等待处理的请求数
+    // alternate 和请求的地址族要一致
+    pub fn check(&self, queued: usize, remote_addr: SocketAddr) -> Option<SocketAddr> {
+        if self.alternate.is_ipv4() != remote_addr.is_ipv4() {
+            return None;
+        }
+
+        match self.policy {
+            RedirectPolicy::Always => Some(self.alternate),
+            RedirectPolicy::QueueAbove(v) if queued > v => Some(self.alternate),
+            RedirectPolicy::QueueAbove(_) => None,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn decode_options(&self, port: u16) -> DecodeOptions {
+        match self.decode.get(&port) {
+            Some(v) => v.clone(),
+            None => DecodeOptions::default(),
+        }
+    }
+}
+
+type SocketMap = HashMap<SocketAddr, Arc<UdpSocket>>;
+
+// 没有认证和重定向时, binding request 直接在 recv_udp 中处理, 不复制也不进队列
+#[derive(Clone)]
+struct FastPath {
+    ips: [IpAddr; 2],
+    ports: [u16; 2],
+    sockets: Arc<SocketMap>,
+    software: Option<RawAttr>,
+}
+
 pub struct Server {
     ips: [IpAddr; 2],
     ports: [u16; 2],
+    config: ServerConfig,
     signal_rx: WatchReceiver<u8>,
     queue_tx: Arc<Sender<SocketInput>>,
     queue_rx: Receiver<SocketInput>,
-    sockets: HashMap<SocketAddr, Arc<UdpSocket>>,
+    sockets: Arc<SocketMap>,
 }
 
 impl Server {
     pub async fn new(
         ips: [IpAddr; 2],
         ports: [u16; 2],
+        config: ServerConfig,
         signal_rx: WatchReceiver<u8>,
     ) -> io::Result<Self> {
-        let (queue_tx, queue_rx) = mpsc::channel::<SocketInput>(100);
+        let (queue_tx, queue_rx) = mpsc::channel::<SocketInput>(QUEUE_LEN);
         let map = init_socket(ips, ports).await?;
 
         let server = Self {
             ips,
             ports,
+            config,
             signal_rx,
             queue_tx: Arc::new(queue_tx),
             queue_rx,
-            sockets: map,
+            sockets: Arc::new(map),
         };
         Ok(server)
     }
@@ -54,14 +138,26 @@ impl Server {
     pub async fn run(self) {
         let mut handles = vec![];
 
+        let fast_path = match (&self.config.auth, &self.config.redirect) {
+            (None, None) => Some(FastPath {
+                ips: self.ips,
+                ports: self.ports,
+                sockets: self.sockets.clone(),
+                software: self.config.software.clone().map(|x| x.into()),
+            }),
+            _ => None,
+        };
+
         for (addr, udp) in self.sockets.iter() {
             let socket = udp.clone();
             let local_addr = *addr;
             let sender = self.queue_tx.clone();
             let signal_rx = self.signal_rx.clone();
+            let fast_path = fast_path.clone();
+            let options = self.config.decode_options(local_addr.port());
 
             let h = tokio::spawn(async move {
-                recv_udp(socket, local_addr, sender, signal_rx).await;
+                recv_udp(socket, local_addr, sender, signal_rx, fast_path, options).await;
             });
             handles.push(h);
         }
@@ -69,9 +165,11 @@ impl Server {
         let h = tokio::spawn(async move {
             process_udp(
                 self.queue_rx,
+                self.queue_tx,
                 self.signal_rx,
                 self.ips,
                 self.ports,
+                self.config,
                 self.sockets,
             )
             .await;
@@ -86,10 +184,7 @@ impl Server {
 
 //--------------------------------------------------
 
-async fn init_socket(
-    ips: [IpAddr; 2],
-    ports: [u16; 2],
-) -> io::Result<HashMap<SocketAddr, Arc<UdpSocket>>> {
+async fn init_socket(ips: [IpAddr; 2], ports: [u16; 2]) -> io::Result<SocketMap> {
     let mut sockets = HashMap::with_capacity(4);
 
     // bind, 互为 CA, CP
@@ -110,16 +205,31 @@ async fn recv_udp(
     local_addr: SocketAddr,
     sender: Arc<Sender<SocketInput>>,
     mut signal_rx: WatchReceiver<u8>,
+    fast_path: Option<FastPath>,
+    options: DecodeOptions,
 ) {
     let mut buf = vec![0u8; 32 * 1024];
+    let mut send_buf = vec![0u8; SEND_BUF_LEN];
 
     loop {
         tokio::select! {
             Ok((len,remote_addr)) = socket.recv_from(&mut buf) => {
-                let data = Bytes::copy_from_slice(&buf[..len]);
+                debug!("recv len: {}", len);
+                debug!("{} <--- {}\n{}",local_addr,remote_addr,print_packet(&buf[..len]));
 
-                debug!("recv len: {}", data.len());
-                debug!("{} <--- {}\n{}",local_addr,remote_addr,print_bytes(&data," ",8));
+                if let Some(fast) = &fast_path {
+                    let data = &buf[..len];
+                    let result = encode_fast_response(
+                        data, &options, local_addr, remote_addr, fast.ips, fast.ports,
+                        fast.software.as_ref(), &mut send_buf,
+                    );
+                    if let Some((n, src_addr, dst_addr)) = result {
+                        send_bytes(&send_buf[..n], src_addr, dst_addr, &fast.sockets).await;
+                        continue;
+                    }
+                }
+
+                let data = Bytes::copy_from_slice(&buf[..len]);
 
                 match sender.send((local_addr,remote_addr,data)).await {
                     Ok(_) => {}
@@ -138,15 +248,21 @@ async fn recv_udp(
 
 async fn process_udp(
     mut receiver: Receiver<SocketInput>,
+    sender: Arc<Sender<SocketInput>>,
     mut signal_rx: WatchReceiver<u8>,
     ips: [IpAddr; 2],
     ports: [u16; 2],
-    sockets: HashMap<SocketAddr, Arc<UdpSocket>>,
+    config: ServerConfig,
+    sockets: Arc<SocketMap>,
 ) {
+    let mut send_buf = vec![0u8; SEND_BUF_LEN];
+
     loop {
         tokio::select! {
             Some(input) = receiver.recv() => {
-               process_one(input,ips,ports,&sockets).await;
+               // 取出当前这个之后, 还在排队的请求数
+               let queued = QUEUE_LEN - sender.capacity();
+               process_one(input,queued,ips,ports,&config,&sockets,&mut send_buf).await;
             },
              _ = signal_rx.changed() => {
                 debug!("recv signal, process_input, will exit.");
@@ -158,40 +274,145 @@ async fn process_udp(
 
 async fn process_one(
     input: SocketInput,
+    queued: usize,
     ips: [IpAddr; 2],
     ports: [u16; 2],
-    sockets: &HashMap<SocketAddr, Arc<UdpSocket>>,
+    config: &ServerConfig,
+    sockets: &SocketMap,
+    send_buf: &mut [u8],
 ) {
     // 解析请求数据包
     // 组装响应包
     // 找到对应的socket 发送
 
     let (local_addr, remote_addr, buf) = input;
-    let request = match parse_request(buf) {
+    let decode_options = config.decode_options(local_addr.port());
+    let request = match parse_request(buf.clone(), &decode_options) {
         Ok(v) => v,
         Err(e) => {
             error!(
-                "parse error, from remote:{}, local:{}, {:?}",
+                "parse error, from remote:{}, local:{}, {}",
                 remote_addr, local_addr, e
             );
+
+            // 格式错误的 binding request 回复 400, 其他的直接丢弃
+            if let Some((header, code)) = parse_error_code(&buf, &e) {
+                let (mut response, src_addr, dst_addr) =
+                    get_error_response(&header, code, local_addr, remote_addr);
+                add_software(&mut response, config);
+                let options = PackOptions::default();
+                send_response(&response, &options, src_addr, dst_addr, sockets, send_buf).await;
+            }
             return;
         }
     };
 
+    // 请求带了 fingerprint, 响应也带上
+    let options = PackOptions {
+        fingerprint: request.has_attr(ATTR_FINGERPRINT),
+        ..Default::default()
+    };
+
     if let Some(e) = validate_req(&request) {
         error!(
             "validate error, from remote:{}, local:{}, {}",
             remote_addr, local_addr, e
         );
 
-        // send err response
-        let (response, src_addr, dst_addr) = get_bad_response(&request, local_addr, remote_addr);
-        send_response(&response, src_addr, dst_addr, sockets).await;
+        // fingerprint 不对的直接丢弃
+        if let Some(code) = e.error_code() {
+            let (mut response, src_addr, dst_addr) =
+                get_error_response(&request.header, code, local_addr, remote_addr);
+            add_software(&mut response, config);
+            send_response(&response, &options, src_addr, dst_addr, sockets, send_buf).await;
+        }
 
         return;
     }
 
-    let (response, src_addr, dst_addr) =
-        get_response(&request, local_addr, remote_addr, ips, ports);
-    send_response(&response, src_addr, dst_addr, sockets).await;
+    // long-term credential
+    let key = match &config.auth {
+        None => None,
+        Some(auth) => match auth.check(&request, remote_addr.ip()) {
+            AuthResult::Pass(key, kind) => Some((key, kind)),
+            AuthResult::Challenge(code) => {
+                debug!(
+                    "auth challenge {}, from remote:{}, local:{}",
+                    code, remote_addr, local_addr
+                );
+
+                let (mut response, src_addr, dst_addr) =
+                    get_challenge_response(&request, code, auth, local_addr, remote_addr);
+                add_software(&mut response, config);
+                send_response(&response, &options, src_addr, dst_addr, sockets, send_buf).await;
+                return;
+            }
+            AuthResult::BadRequest => {
+                error!(
+                    "auth bad request, from remote:{}, local:{}",
+                    remote_addr, local_addr
+                );
+
+                let (mut response, src_addr, dst_addr) = get_error_response(
+                    &request.header,
+                    ERROR_CODE_BAD_REQUEST,
+                    local_addr,
+                    remote_addr,
+                );
+                add_software(&mut response, config);
+                send_response(&response, &options, src_addr, dst_addr, sockets, send_buf).await;
+                return;
+            }
+        },
+    };
+
+    // 认证通过之后再检查不认识的 attribute 和重定向
+    let unknown = request.unknown_attrs();
+    let alternate = match &config.redirect {
+        None => None,
+        Some(v) => v.check(queued, remote_addr),
+    };
+    let (mut response, src_addr, dst_addr) = match (unknown.is_empty(), alternate) {
+        (true, None) => get_response(
+            &request.header,
+            get_change_flag(&request),
+            local_addr,
+            remote_addr,
+            ips,
+            ports,
+        ),
+        (true, Some(alternate)) => {
+            debug!(
+                "redirect to {}, queued: {}, from remote:{}, local:{}",
+                alternate, queued, remote_addr, local_addr
+            );
+            get_alternate_response(&request.header, alternate, local_addr, remote_addr)
+        }
+        (false, _) => {
+            debug!(
+                "unknown attrs: {:x?}, from remote:{}, local:{}",
+                unknown, remote_addr, local_addr
+            );
+            get_unknown_attrs_response(&request, unknown, local_addr, remote_addr)
+        }
+    };
+
+    // message-integrity 之前
+    add_software(&mut response, config);
+    let result = match key {
+        None => Ok(()),
+        Some((key, IntegrityKind::Sha1)) => response.add_message_integrity(&key),
+        Some((key, IntegrityKind::Sha256)) => response.add_message_integrity_sha256(&key),
+    };
+    if let Err(e) = result {
+        error!("encode error, {} ---> {}, {:?}", src_addr, dst_addr, e);
+        return;
+    }
+    send_response(&response, &options, src_addr, dst_addr, sockets, send_buf).await;
+}
+
+fn add_software(res: &mut Packet, config: &ServerConfig) {
+    if let Some(v) = &config.software {
+        res.add_attr(v.clone().into());
+    }
 }
@@ -1,4 +1,4 @@
-use bytes::Bytes;
+use bytes::{BufMut, Bytes};
 use log::{debug, error};
 use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
@@ -6,26 +6,50 @@ use std::sync::Arc;
 use stun_rs::attrs::address_attr::AddressAttr;
 use stun_rs::attrs::change_request::ChangeRequest;
 use stun_rs::attrs::errcode_attr::ErrcodeAttr;
+use stun_rs::attrs::fingerprint::Fingerprint;
+use stun_rs::attrs::nonce::Nonce;
+use stun_rs::attrs::realm::Realm;
 use stun_rs::attrs::response_port::ResponsePort;
+use stun_rs::attrs::unknown_attributes::UnknownAttributes;
 use stun_rs::attrs::xor_address::XorMappedAddress;
+use stun_rs::attrs::{AttrPadding, RawAttr};
 use stun_rs::constants::*;
 use tokio::net::UdpSocket;
 
+use stun_rs::error::{ParsePacketErr, ValidateErr};
 use stun_rs::header::Header;
-use stun_rs::packet::Packet;
-use stun_rs::util::print_bytes;
+use stun_rs::packet::{DecodeOptions, PackOptions, Packet};
+use stun_rs::packet_ref::PacketRef;
+use stun_rs::util::print_packet;
 
-pub fn parse_request(buf: Bytes) -> Result<Packet, String> {
-    Packet::unpack(buf).map_err(|x| format!("{:?}", x))
+use crate::auth::Authenticator;
+
+pub fn parse_request(buf: Bytes, options: &DecodeOptions) -> Result<Packet, ParsePacketErr> {
+    Packet::unpack_with(buf, options)
 }
 
-pub fn validate_req(req: &Packet) -> Option<String> {
+// 解析失败时, 能确定是 binding request 的才回复错误响应
+// 返回请求的 header 和错误码
+pub fn parse_error_code(buf: &[u8], e: &ParsePacketErr) -> Option<(Header, u16)> {
+    let code = e.error_code()?;
+    let header = Header::unpack_from(buf).ok()?;
+    if header.msg_type != MESSAGE_TYPE_BIND_REQ {
+        return None;
+    }
+
+    Some((header, code))
+}
+
+pub fn validate_req(req: &Packet) -> Option<ValidateErr> {
     if req.header.msg_type != MESSAGE_TYPE_BIND_REQ {
-        return Some(format!("bad request msg_type: {}", req.header.msg_type));
+        return Some(ValidateErr::Other(format!(
+            "bad request msg_type: {:?}",
+            req.header.msg_type
+        )));
     }
 
     if let Some(e) = req.validate() {
-        return Some(format!("validate fail, {:?}", e));
+        return Some(e);
     }
 
     // check response-port && padding
@@ -42,51 +66,163 @@ pub fn validate_req(req: &Packet) -> Option<String> {
     }
 
     if has_res_port && has_padding {
-        return Some("RESPONSE_PORT and PADDING appear at the same time".to_string());
+        return Some(ValidateErr::Other(
+            "RESPONSE_PORT and PADDING appear at the same time".to_string(),
+        ));
     }
 
     None
 }
 
-// 返回响应包，响应包从哪个地址发出，发到哪个目的地址
+// 没有认证时, 普通的 binding request 直接使用接收的 buf 解析, 响应写到 out, 不分配内存
+// 返回响应的长度, 响应包从哪个地址发出, 发到哪个目的地址
+// 返回 None 时走正常的流程 (错误响应等)
+#[allow(clippy::too_many_arguments)]
+pub fn encode_fast_response(
+    buf: &[u8],
+    options: &DecodeOptions,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+    ips: [IpAddr; 2],
+    ports: [u16; 2],
+    software: Option<&RawAttr>,
+    out: &mut [u8],
+) -> Option<(usize, SocketAddr, SocketAddr)> {
+    let req = PacketRef::parse_with(buf, options).ok()?;
+
+    if req.header.msg_type != MESSAGE_TYPE_BIND_REQ
+        || req.validate().is_some()
+        || req.has_unknown_attrs()
+        || (req.has_attr(ATTR_RESPONSE_PORT) && req.has_attr(ATTR_PADDING))
+    {
+        return None;
+    }
 
+    let (src_addr, dst_addr, changed_addr) = get_route(
+        get_change_flag_ref(&req),
+        local_addr,
+        remote_addr,
+        ips,
+        ports,
+    );
+    let attrs = get_address_attrs(&req.header, local_addr, remote_addr, changed_addr);
+
+    // 请求带了 fingerprint, 响应也带上
+    let fingerprint = req.has_attr(ATTR_FINGERPRINT);
+
+    let mut msg_len = attrs
+        .iter()
+        .flatten()
+        .fold(0, |acc, x| acc + 4 + x.value_len());
+    if let Some(v) = software {
+        msg_len += v.len();
+    }
+    if fingerprint {
+        msg_len += 4 + FINGERPRINT_LEN;
+    }
+
+    let total = HEADER_LEN + msg_len;
+    if out.len() < total {
+        return None;
+    }
+
+    let mut header = req.header.reply(MESSAGE_TYPE_BIND_RES);
+    header.msg_len = msg_len as u16;
+
+    let mut w = &mut out[..];
+    let mut offset = header.encode(&mut w);
+    for v in attrs.iter().flatten() {
+        offset += v.encode(&mut w);
+    }
+    if let Some(v) = software {
+        offset += v.encode(&mut w, AttrPadding::Padded);
+    }
+
+    if fingerprint {
+        let attr = Fingerprint::calculate(&out[..offset]);
+        let mut w = &mut out[offset..];
+        w.put_u16(ATTR_FINGERPRINT);
+        w.put_u16(FINGERPRINT_LEN as u16);
+        w.put_u32(attr.crc);
+    }
+
+    Some((total, src_addr, dst_addr))
+}
+
+// 返回响应包，响应包从哪个地址发出，发到哪个目的地址
 pub fn get_response(
-    req: &Packet,
+    req: &Header,
+    change_flag: (bool, bool, Option<u16>),
     local_addr: SocketAddr,
     remote_addr: SocketAddr,
     ips: [IpAddr; 2],
     ports: [u16; 2],
 ) -> (Packet, SocketAddr, SocketAddr) {
-    let trans_id = req.header.trans_id;
-    let header = Header::new(MESSAGE_TYPE_BIND_RES, 0, trans_id);
+    let header = req.reply(MESSAGE_TYPE_BIND_RES);
+
+    let (src_addr, dst_addr, changed_addr) =
+        get_route(change_flag, local_addr, remote_addr, ips, ports);
+
+    let attrs = get_address_attrs(req, local_addr, remote_addr, changed_addr)
+        .into_iter()
+        .flatten()
+        .map(|x| x.into())
+        .collect();
 
+    (Packet::new(header, attrs), src_addr, dst_addr)
+}
+
+// rfc 3489 的客户端只认识 mapped-address, source-address, changed-address
+fn get_address_attrs(
+    req: &Header,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+    changed_addr: SocketAddr,
+) -> [Option<AddressAttr>; 6] {
     let mapped_address_attr = AddressAttr::new(ATTR_MAPPED_ADDRESS, remote_addr);
-    let response_origin_attr = AddressAttr::new(ATTR_RESPONSE_ORIGIN, local_addr);
     let source_address_attr = AddressAttr::new(ATTR_SOURCE_ADDRESS, local_addr);
-    let xor_mapped_attr = XorMappedAddress::new(trans_id, remote_addr);
+    let changed_address_attr = AddressAttr::new(ATTR_CHANGED_ADDRESS, changed_addr);
+
+    match req.is_classic() {
+        true => [
+            Some(mapped_address_attr),
+            Some(source_address_attr),
+            Some(changed_address_attr),
+            None,
+            None,
+            None,
+        ],
+        false => {
+            let response_origin_attr = AddressAttr::new(ATTR_RESPONSE_ORIGIN, local_addr);
+            let xor_mapped_attr = XorMappedAddress::from_header(req, remote_addr);
+            let other_address_attr = AddressAttr::new(ATTR_OTHER_ADDRESS, changed_addr);
+
+            [
+                Some(mapped_address_attr),
+                Some(response_origin_attr),
+                Some(source_address_attr),
+                Some(xor_mapped_attr.to_address_attr()),
+                Some(other_address_attr),
+                Some(changed_address_attr),
+            ]
+        }
+    }
+}
 
+// 检查 change-request / response-port
+// 返回: 从哪个地址发出, 发到哪个地址, changed-address
+fn get_route(
+    change_flag: (bool, bool, Option<u16>),
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+    ips: [IpAddr; 2],
+    ports: [u16; 2],
+) -> (SocketAddr, SocketAddr, SocketAddr) {
     let da = local_addr.ip();
     let dp = local_addr.port();
     let (ca, cp) = get_ca_cp(da, dp, ips, ports);
 
-    let other_address_attr = AddressAttr::new(ATTR_OTHER_ADDRESS, SocketAddr::new(ca, cp));
-
-    let changed_address_attr = AddressAttr::new(ATTR_CHANGED_ADDRESS, SocketAddr::new(ca, cp));
-
-    let attrs = vec![
-        mapped_address_attr.into(),
-        response_origin_attr.into(),
-        source_address_attr.into(),
-        xor_mapped_attr.into(),
-        other_address_attr.into(),
-        changed_address_attr.into(),
-    ];
-
-    let response = Packet::new(header, attrs);
-
-    // 检查 change-request / response-port
-
-    let (change_ip, change_port, response_port) = get_change_flag(req);
+    let (change_ip, change_port, response_port) = change_flag;
 
     let dst_addr = match response_port {
         None => remote_addr,
@@ -104,30 +240,33 @@ pub fn get_response(
 
     let src_addr = SocketAddr::new(src_ip, src_port);
 
-    (response, src_addr, dst_addr)
+    (src_addr, dst_addr, SocketAddr::new(ca, cp))
 }
 
 pub fn get_change_flag(req: &Packet) -> (bool, bool, Option<u16>) {
-    let mut change_ip = false;
-    let mut change_port = false;
-    let mut response_port = None;
-
-    for attr in req.attrs.iter() {
-        if attr.attr_type == ATTR_CHANGE_REQUEST {
-            let change_attr: Result<ChangeRequest, _> = attr.clone().try_into();
-            if let Ok(v) = change_attr {
-                change_ip = v.change_ip;
-                change_port = v.change_port;
-            }
-        }
+    let (change_ip, change_port) = match req.get::<ChangeRequest>() {
+        Ok(Some(v)) => (v.change_ip, v.change_port),
+        _ => (false, false),
+    };
 
-        if attr.attr_type == ATTR_RESPONSE_PORT {
-            let change_attr: Result<ResponsePort, _> = attr.clone().try_into();
-            if let Ok(v) = change_attr {
-                response_port = Some(v.port);
-            }
-        }
-    }
+    let response_port = match req.get::<ResponsePort>() {
+        Ok(Some(v)) => Some(v.port),
+        _ => None,
+    };
+
+    (change_ip, change_port, response_port)
+}
+
+pub fn get_change_flag_ref(req: &PacketRef) -> (bool, bool, Option<u16>) {
+    let (change_ip, change_port) = match req.get::<ChangeRequest>() {
+        Ok(Some(v)) => (v.change_ip, v.change_port),
+        _ => (false, false),
+    };
+
+    let response_port = match req.get::<ResponsePort>() {
+        Ok(Some(v)) => Some(v.port),
+        _ => None,
+    };
 
     (change_ip, change_port, response_port)
 }
@@ -146,22 +285,119 @@ pub fn get_ca_cp(da: IpAddr, dp: u16, ips: [IpAddr; 2], ports: [u16; 2]) -> (IpA
     (ca, cp)
 }
 
-pub fn get_bad_response(
+// code 一般来自 ParsePacketErr / ValidateErr 的 error_code
+pub fn get_error_response(
+    req: &Header,
+    code: u16,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+) -> (Packet, SocketAddr, SocketAddr) {
+    let header = req.reply(MESSAGE_TYPE_BIND_ERR_RES);
+
+    let mut res = Packet::new(header, vec![]);
+    res.add_attr(ErrcodeAttr::new(code, error_reason(code)).into());
+
+    (res, local_addr, remote_addr)
+}
+
+// rfc 5389, 7.3.1
+// 420, 列出不认识的 comprehension-required attribute
+pub fn get_unknown_attrs_response(
     req: &Packet,
+    attr_types: Vec<u16>,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+) -> (Packet, SocketAddr, SocketAddr) {
+    let header = req.header.reply(MESSAGE_TYPE_BIND_ERR_RES);
+
+    let mut res = Packet::new(header, vec![]);
+    res.add_attr(
+        ErrcodeAttr::new(
+            ERROR_CODE_UNKNOWN_ATTRIBUTE,
+            error_reason(ERROR_CODE_UNKNOWN_ATTRIBUTE),
+        )
+        .into(),
+    );
+    res.add_attr(UnknownAttributes::new(attr_types).into());
+
+    (res, local_addr, remote_addr)
+}
+
+// rfc 8489, 10
+// 300, 让客户端换到 alternate-server 重新请求
+pub fn get_alternate_response(
+    req: &Header,
+    alternate: SocketAddr,
     local_addr: SocketAddr,
     remote_addr: SocketAddr,
 ) -> (Packet, SocketAddr, SocketAddr) {
-    let trans_id = req.header.trans_id;
-    let header = Header::new(MESSAGE_TYPE_BIND_ERR_RES, 0, trans_id);
+    let header = req.reply(MESSAGE_TYPE_BIND_ERR_RES);
 
     let mut res = Packet::new(header, vec![]);
-    res.add_attr(ErrcodeAttr::new(ERROR_CODE_BAD_REQUEST, "bad request").into());
+    res.add_attr(
+        ErrcodeAttr::new(
+            ERROR_CODE_TRY_ALTERNATE,
+            error_reason(ERROR_CODE_TRY_ALTERNATE),
+        )
+        .into(),
+    );
+    res.add_attr(AddressAttr::new(ATTR_ALTERNATE_SERVER, alternate).into());
 
     (res, local_addr, remote_addr)
 }
 
+// 401 / 438, 带上 realm, 新的 nonce 和 password-algorithms
+pub fn get_challenge_response(
+    req: &Packet,
+    code: u16,
+    auth: &Authenticator,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+) -> (Packet, SocketAddr, SocketAddr) {
+    let header = req.header.reply(MESSAGE_TYPE_BIND_ERR_RES);
+
+    let mut res = Packet::new(header, vec![]);
+    res.add_attr(ErrcodeAttr::new(code, error_reason(code)).into());
+    res.add_attr(Realm::new(auth.realm()).into());
+    res.add_attr(Nonce::new(&auth.new_nonce(remote_addr.ip())).into());
+    res.add_attr(auth.password_algorithms().into());
+
+    (res, local_addr, remote_addr)
+}
+
+pub fn error_reason(code: u16) -> &'static str {
+    match code {
+        ERROR_CODE_TRY_ALTERNATE => "try alternate",
+        ERROR_CODE_BAD_REQUEST => "bad request",
+        ERROR_CODE_UNAUTHORIZED => "unauthorized",
+        ERROR_CODE_UNKNOWN_ATTRIBUTE => "unknown attribute",
+        ERROR_CODE_STALE_NONCE => "stale nonce",
+        _ => "error",
+    }
+}
+
+// buf: 重复使用的发送 buf
 pub async fn send_response(
     res: &Packet,
+    options: &PackOptions,
+    src_addr: SocketAddr,
+    dst_addr: SocketAddr,
+    sockets: &HashMap<SocketAddr, Arc<UdpSocket>>,
+    buf: &mut [u8],
+) {
+    let len = match res.encode_to_slice(buf, options) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("encode error, {} ---> {}, {:?}", src_addr, dst_addr, e);
+            return;
+        }
+    };
+
+    send_bytes(&buf[..len], src_addr, dst_addr, sockets).await;
+}
+
+pub async fn send_bytes(
+    data: &[u8],
     src_addr: SocketAddr,
     dst_addr: SocketAddr,
     sockets: &HashMap<SocketAddr, Arc<UdpSocket>>,
@@ -171,18 +407,12 @@ pub async fn send_response(
             error!("can't find UdpSocket: {}", src_addr);
             return;
         }
-        Some(v) => v.clone(),
+        Some(v) => v,
     };
 
-    let data = res.pack();
-    match socket.send_to(&data, dst_addr).await {
+    match socket.send_to(data, dst_addr).await {
         Ok(v) => {
-            debug!(
-                "{} ---> {}\n{}",
-                src_addr,
-                dst_addr,
-                print_bytes(&data, " ", 8)
-            );
+            debug!("{} ---> {}\n{}", src_addr, dst_addr, print_packet(data));
             debug!("sent: {}", v);
         }
         Err(e) => {
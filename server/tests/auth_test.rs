@@ -0,0 +1,62 @@
+use server::auth::{AuthResult, Authenticator};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+use stun_rs::attrs::nonce::Nonce;
+use stun_rs::attrs::realm::Realm;
+use stun_rs::attrs::username::Username;
+use stun_rs::auth;
+use stun_rs::constants::*;
+use stun_rs::header::Header;
+use stun_rs::packet::Packet;
+use stun_rs::util;
+
+fn new_request(nonce: &str) -> Packet {
+    let header = Header::new(MESSAGE_TYPE_BIND_REQ, 0, util::new_trans_id());
+    let mut packet = Packet::new(
+        header,
+        vec![
+            Username::new("alice").into(),
+            Realm::new("example.org").into(),
+            Nonce::new(nonce).into(),
+        ],
+    );
+    packet
+        .add_message_integrity(&auth::long_term_key("alice", "example.org", "secret"))
+        .unwrap();
+    packet
+}
+
+#[test]
+pub fn test_check_nonce() {
+    let users = HashMap::from([("alice".to_string(), "secret".to_string())]);
+    let authenticator = Authenticator::new(
+        "example.org",
+        users,
+        vec![PASSWORD_ALGORITHM_MD5],
+        Duration::from_secs(60),
+    );
+    let remote_ip: IpAddr = "127.0.0.1".parse().unwrap();
+
+    let nonce = authenticator.new_nonce(remote_ip);
+    let result = authenticator.check(&new_request(&nonce), remote_ip);
+    assert!(matches!(result, AuthResult::Pass(_, _)));
+
+    let result = authenticator.check(
+        &new_request(&format!("{}{}", NONCE_COOKIE, "0".repeat(36))),
+        remote_ip,
+    );
+    assert!(matches!(
+        result,
+        AuthResult::Challenge(ERROR_CODE_STALE_NONCE)
+    ));
+
+    // 时间戳的最后一个字节在多字节字符中间
+    let nonce = format!("{}{}\u{e9}{}", NONCE_COOKIE, "0".repeat(19), "0".repeat(15));
+    assert_eq!(nonce.len(), NONCE_COOKIE_LEN + 32);
+    let result = authenticator.check(&new_request(&nonce), remote_ip);
+    assert!(matches!(
+        result,
+        AuthResult::Challenge(ERROR_CODE_STALE_NONCE)
+    ));
+}